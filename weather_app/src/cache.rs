@@ -0,0 +1,161 @@
+use crate::{Error, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Environment variable that moves the cache, below `--cache-dir`.
+pub const CACHE_DIR_ENV: &str = "WEATHER_CACHE_DIR";
+
+/// Picks the cache directory from, in order: the `--cache-dir` flag, the
+/// `WEATHER_CACHE_DIR` environment variable, then the user's cache
+/// directory. An empty variable counts as unset.
+fn cache_dir(flag: Option<PathBuf>, env: Option<OsString>) -> Option<PathBuf> {
+    flag.or_else(|| env.filter(|dir| !dir.is_empty()).map(PathBuf::from))
+        .or_else(|| Some(dirs::cache_dir()?.join("weather_app")))
+}
+
+/// Turns a cache key into a safe file name.
+fn file_stem(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A raw API response as it is stored on disk.
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    fetched_at: i64,
+    body: String,
+}
+
+/// Best-effort on-disk cache of raw API responses.
+///
+/// Responses are stored exactly as received (temperatures in Kelvin), so the
+/// display unit doesn't need to be part of the key. Any I/O or parse failure
+/// is treated as a cache miss rather than an error.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl Cache {
+    /// Opens the cache in `dir` (from `--cache-dir`), `WEATHER_CACHE_DIR` or
+    /// the user's cache directory, creating it if it's missing. `None` when
+    /// there is nowhere to put it.
+    pub fn open(dir: Option<PathBuf>, ttl: Duration) -> Result<Option<Self>> {
+        let Some(dir) = cache_dir(dir, env::var_os(CACHE_DIR_ENV)) else {
+            return Ok(None);
+        };
+        fs::create_dir_all(&dir).map_err(|e| match e.kind() {
+            ErrorKind::PermissionDenied => Error::Config(format!(
+                "No permission to create the cache directory {}; point --cache-dir or {} \
+                 somewhere writable, or pass --no-cache",
+                dir.display(),
+                CACHE_DIR_ENV
+            )),
+            _ => Error::Other(format!(
+                "Failed to create the cache directory {}: {}",
+                dir.display(),
+                e
+            )),
+        })?;
+        Ok(Some(Cache { dir, ttl }))
+    }
+
+    /// Opens a cache rooted at `dir` instead of the user's cache directory.
+    pub fn at(dir: PathBuf, ttl: Duration) -> Self {
+        Cache { dir, ttl }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", file_stem(key)))
+    }
+
+    fn entry(&self, key: &str) -> Option<CacheEntry> {
+        let contents = fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Returns the cached body for `key` if it is younger than the TTL.
+    pub fn get(&self, key: &str) -> Option<String> {
+        let entry = self.entry(key)?;
+        let age = Utc::now().timestamp() - entry.fetched_at;
+        if age >= 0 && (age as u64) < self.ttl.as_secs() {
+            Some(entry.body)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the cached body for `key` however old it is.
+    pub fn get_stale(&self, key: &str) -> Option<String> {
+        self.entry(key).map(|entry| entry.body)
+    }
+
+    /// Returns the cached bodies of every key starting with `prefix`, however
+    /// old they are.
+    pub fn get_all_stale(&self, prefix: &str) -> Vec<String> {
+        let prefix = file_stem(prefix);
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        dir.flatten()
+            .filter(|file| file.file_name().to_string_lossy().starts_with(&prefix))
+            .filter_map(|file| fs::read_to_string(file.path()).ok())
+            .filter_map(|contents| serde_json::from_str::<CacheEntry>(&contents).ok())
+            .map(|entry| entry.body)
+            .collect()
+    }
+
+    pub fn put(&self, key: &str, body: &str) {
+        let entry = CacheEntry {
+            fetched_at: Utc::now().timestamp(),
+            body: body.to_string(),
+        };
+        if let Ok(contents) = serde_json::to_string(&entry) {
+            let _ = fs::create_dir_all(&self.dir);
+            let _ = fs::write(self.path(key), contents);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_beats_env_and_empty_env_is_ignored() {
+        let flag = PathBuf::from("/from/flag");
+        let env = OsString::from("/from/env");
+        assert_eq!(cache_dir(Some(flag.clone()), Some(env.clone())), Some(flag));
+        assert_eq!(cache_dir(None, Some(env)), Some(PathBuf::from("/from/env")));
+        assert_eq!(
+            cache_dir(None, Some(OsString::new())),
+            dirs::cache_dir().map(|dir| dir.join("weather_app"))
+        );
+    }
+
+    #[test]
+    fn open_creates_a_missing_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("nested");
+        let cache = Cache::open(Some(dir.clone()), Duration::from_secs(60))
+            .unwrap()
+            .unwrap();
+        assert!(dir.is_dir());
+        cache.put("seattle", "{}");
+        assert_eq!(cache.get("seattle").as_deref(), Some("{}"));
+    }
+}
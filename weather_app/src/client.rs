@@ -0,0 +1,1663 @@
+use crate::provider::place_name;
+use crate::{
+    AirQuality, Cache, Clouds, Coord, Error, ForecastCity, ForecastEntry, ForecastResponse,
+    Location, Main, Precipitation, Result, Sys, Weather, WeatherResponse, Wind,
+};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use clap::ValueEnum;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Root the `data/2.5/...` and `geo/1.0/...` endpoints live under.
+pub const API_BASE_URL: &str = "https://api.openweathermap.org";
+
+/// Host of the statistical (historical) endpoints.
+const HISTORY_BASE_URL: &str = "https://history.openweathermap.org";
+
+/// Language weather descriptions are returned in unless `--lang` says otherwise.
+pub const DEFAULT_LANG: &str = "en";
+
+/// How many geocoding candidates to ask for when resolving a city name.
+const GEOCODING_LIMIT: usize = 5;
+
+/// Sent with every request so providers can tell this tool's traffic apart
+/// from anonymous scripts, which some rate-limit or block.
+pub const USER_AGENT: &str = concat!("weather-cli/", env!("CARGO_PKG_VERSION"));
+
+/// Default time allowed for a single request before giving up.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default number of extra attempts made after a transient failure.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Remaining calls at or below which `--verbose` warns about the quota.
+const LOW_QUOTA: u64 = 10;
+
+/// Delay before the first retry; doubled after every further attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Checks that `input` is an http(s) URL to use in place of
+/// [`API_BASE_URL`], e.g. a proxy or a compatible mirror, and returns it
+/// without a trailing slash.
+pub fn parse_base_url(input: &str) -> Result<String> {
+    let url = input.trim().trim_end_matches('/');
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => {
+            Ok(url.to_string())
+        }
+        _ => Err(Error::Config(format!(
+            "Invalid base URL '{}'; expected something like '{}'",
+            input.trim(),
+            API_BASE_URL
+        ))),
+    }
+}
+
+/// Message for a key without a One Call subscription; `--api one-call` falls
+/// back to the 2.5 endpoints when it sees it.
+const ONE_CALL_UNSUBSCRIBED: &str = "This API key isn't subscribed to One Call API 3.0";
+
+/// Which OpenWeatherMap endpoints the weather and forecast come from, chosen
+/// with `--api`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Api {
+    /// The free 2.5 current weather and 5-day forecast endpoints
+    #[default]
+    #[value(name = "2.5")]
+    Classic,
+    /// One Call API 3.0, which needs its own subscription
+    OneCall,
+}
+
+/// OpenWeatherMap API client sharing one HTTP connection pool across requests.
+#[derive(Clone)]
+pub struct WeatherClient {
+    http: reqwest::Client,
+    base_url: String,
+    history_base_url: String,
+    api_key: String,
+    timeout: Duration,
+    retries: u32,
+    cache: Option<Cache>,
+    country: Option<String>,
+    lang: String,
+    verbose: bool,
+    no_emoji: bool,
+    offline: bool,
+    refresh: bool,
+    api: Api,
+}
+
+// Written out by hand so the API key never ends up in debug output
+impl fmt::Debug for WeatherClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeatherClient")
+            .field("base_url", &self.base_url)
+            .field("history_base_url", &self.history_base_url)
+            .field("api_key", &"REDACTED")
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .field("cache", &self.cache)
+            .field("country", &self.country)
+            .field("lang", &self.lang)
+            .field("verbose", &self.verbose)
+            .field("no_emoji", &self.no_emoji)
+            .field("offline", &self.offline)
+            .field("refresh", &self.refresh)
+            .field("api", &self.api)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A place returned by the geocoding API.
+#[derive(Deserialize, Debug, Clone)]
+pub struct GeoMatch {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub country: String,
+    pub state: Option<String>,
+}
+
+impl fmt::Display for GeoMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.state {
+            Some(state) => write!(f, "{}, {}, {}", self.name, state, self.country),
+            None => write!(f, "{}, {}", self.name, self.country),
+        }
+    }
+}
+
+impl WeatherClient {
+    pub fn new(api_key: String, timeout: Duration) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(USER_AGENT)
+            .build()
+            .map_err(|e| Error::Other(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(WeatherClient {
+            http,
+            base_url: API_BASE_URL.to_string(),
+            history_base_url: HISTORY_BASE_URL.to_string(),
+            api_key,
+            timeout,
+            retries: DEFAULT_RETRIES,
+            cache: None,
+            country: None,
+            lang: DEFAULT_LANG.to_string(),
+            verbose: false,
+            no_emoji: false,
+            offline: false,
+            refresh: false,
+            api: Api::default(),
+        })
+    }
+
+    /// Points the client at another server, e.g. a proxy, a mirror or a mock
+    /// in tests. The statistical endpoints normally served from their own
+    /// host are sent there too.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self.history_base_url = self.base_url.clone();
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Option<Cache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Restricts city name lookups to an ISO 3166 country code.
+    pub fn with_country(mut self, country: Option<String>) -> Self {
+        self.country = country;
+        self
+    }
+
+    /// Sets the language weather descriptions come back in, e.g. `es`.
+    pub fn with_lang(mut self, lang: String) -> Self {
+        self.lang = lang;
+        self
+    }
+
+    /// Echoes every request URL (minus the API key) and raw response body to
+    /// stderr.
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Leaves the emoji off the warnings printed with `--verbose`, as
+    /// `--no-emoji` does for the reports.
+    pub fn with_no_emoji(mut self, no_emoji: bool) -> Self {
+        self.no_emoji = no_emoji;
+        self
+    }
+
+    /// Serves every request from the cache, however old the entry, without
+    /// touching the network.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Always asks the API, skipping cached responses however fresh, but
+    /// still stores what comes back in the cache.
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Fetches the weather and forecast from One Call instead of the 2.5
+    /// endpoints.
+    pub fn with_api(mut self, api: Api) -> Self {
+        self.api = api;
+        self
+    }
+
+    /// Query for the weather and forecast endpoints. Kelvin is requested
+    /// explicitly so a `units` default set elsewhere can't change what the
+    /// conversion code receives.
+    fn weather_query(&self, location: &Location) -> Vec<(&'static str, String)> {
+        let mut query = location.query();
+        query.push(("units", "standard".to_string()));
+        query.push(("lang", self.lang.clone()));
+        query
+    }
+
+    pub async fn fetch_weather(&self, location: &Location) -> Result<WeatherResponse> {
+        if self.api == Api::OneCall {
+            match self.fetch_one_call(location).await {
+                Err(e) if is_unsubscribed(&e) => note_fallback(),
+                result => return result.map(|one_call| one_call.into_weather(location)),
+            }
+        }
+        let query = self.weather_query(location);
+        self.fetch_json(
+            &self.base_url,
+            "data/2.5/weather",
+            &query,
+            location,
+            "weather",
+        )
+        .await
+    }
+
+    /// The current weather body exactly as the API sent it, bypassing the
+    /// cache, for `--raw`.
+    pub async fn fetch_weather_raw(&self, location: &Location) -> Result<String> {
+        if self.api == Api::OneCall {
+            let query = self.one_call_query(location).await?;
+            return self
+                .fetch_text(
+                    &self.base_url,
+                    "data/3.0/onecall",
+                    &query,
+                    location,
+                    "One Call",
+                )
+                .await;
+        }
+        let query = self.weather_query(location);
+        self.fetch_text(
+            &self.base_url,
+            "data/2.5/weather",
+            &query,
+            location,
+            "weather",
+        )
+        .await
+    }
+
+    /// One Call only takes coordinates, so city names are geocoded first.
+    async fn one_call_query(&self, location: &Location) -> Result<Vec<(&'static str, String)>> {
+        let (lat, lon) = match location {
+            Location::Coordinates { lat, lon } => (*lat, *lon),
+            Location::City(city) => {
+                let (lat, lon, _) = self.geocode(city).await?;
+                (lat, lon)
+            }
+            Location::Zip { .. } | Location::CityId(_) => return Err(one_call_location_error()),
+        };
+        let mut query = self.weather_query(&Location::Coordinates { lat, lon });
+        query.push(("exclude", "minutely,alerts".to_string()));
+        Ok(query)
+    }
+
+    /// Current conditions, hourly and daily forecast in one request.
+    async fn fetch_one_call(&self, location: &Location) -> Result<OneCallForecast> {
+        let query = self.one_call_query(location).await?;
+        self.fetch_json(
+            &self.base_url,
+            "data/3.0/onecall",
+            &query,
+            location,
+            "One Call",
+        )
+        .await
+    }
+
+    pub async fn fetch_forecast(&self, location: &Location) -> Result<ForecastResponse> {
+        if self.api == Api::OneCall {
+            match self.fetch_one_call(location).await {
+                Err(e) if is_unsubscribed(&e) => note_fallback(),
+                result => return result.map(|one_call| one_call.into_forecast(location)),
+            }
+        }
+        let query = self.weather_query(location);
+        self.fetch_json(
+            &self.base_url,
+            "data/2.5/forecast",
+            &query,
+            location,
+            "forecast",
+        )
+        .await
+    }
+
+    pub async fn fetch_air_quality(&self, lat: f64, lon: f64) -> Result<AirQuality> {
+        let location = Location::Coordinates { lat, lon };
+        let response: AirPollutionResponse = self
+            .fetch_json(
+                &self.base_url,
+                "data/2.5/air_pollution",
+                &location.query(),
+                &location,
+                "air quality",
+            )
+            .await?;
+        let reading = response.list.into_iter().next().ok_or_else(|| {
+            Error::Api("No air quality data available for this location".to_string())
+        })?;
+        Ok(AirQuality {
+            aqi: reading.main.aqi,
+            pm2_5: reading.components.pm2_5,
+            pm10: reading.components.pm10,
+        })
+    }
+
+    /// Current UV index from the One Call API, which needs its own
+    /// subscription on top of a free OpenWeatherMap key.
+    pub async fn fetch_uv_index(&self, lat: f64, lon: f64) -> Result<f64> {
+        let mut query = Location::Coordinates { lat, lon }.query();
+        query.push(("exclude", "minutely,hourly,daily,alerts".to_string()));
+        query.push(("units", "standard".to_string()));
+        let response: OneCallResponse = self
+            .fetch_json(
+                &self.base_url,
+                "data/3.0/onecall",
+                &query,
+                &Location::Coordinates { lat, lon },
+                "UV index",
+            )
+            .await?;
+        Ok(response.current.uvi)
+    }
+
+    /// Mean temperature in Kelvin recorded on `date`'s day of the year at
+    /// `lat`/`lon`, from the Statistical Weather API. Like One Call, it isn't
+    /// part of the free plan.
+    pub async fn fetch_seasonal_average(&self, lat: f64, lon: f64, date: NaiveDate) -> Result<f64> {
+        let location = Location::Coordinates { lat, lon };
+        let mut query = location.query();
+        query.push(("month", date.month().to_string()));
+        query.push(("day", date.day().to_string()));
+        let response: DayAggregateResponse = self
+            .fetch_json(
+                &self.history_base_url,
+                "data/2.5/aggregated/day",
+                &query,
+                &location,
+                "seasonal average",
+            )
+            .await?;
+        Ok(response.result.temp.mean)
+    }
+
+    /// Looks up every place matching `city` (narrowed by the configured
+    /// country, if any), best match first.
+    pub async fn geocode_all(&self, city: &str) -> Result<Vec<GeoMatch>> {
+        let query = self.geocoding_query(city);
+        let location = Location::City(city.to_string());
+        self.fetch_json(
+            &self.base_url,
+            "geo/1.0/direct",
+            &query,
+            &location,
+            "geocoding",
+        )
+        .await
+    }
+
+    /// The distinct places named like `city` once its qualifiers and the
+    /// configured country are dropped, for suggestions after a lookup such
+    /// as `London,ZZ` found nothing. The geocoding API only matches exact
+    /// names, so this can't correct a misspelled name.
+    pub async fn suggest(&self, city: &str) -> Result<Vec<String>> {
+        let name = place_name(city);
+        if name == city.trim() && self.country.is_none() {
+            // The same query as the lookup that just failed
+            return Ok(Vec::new());
+        }
+        let query = [
+            ("q", name.to_string()),
+            ("limit", GEOCODING_LIMIT.to_string()),
+        ];
+        let location = Location::City(name.to_string());
+        let places: Vec<GeoMatch> = self
+            .fetch_json(
+                &self.base_url,
+                "geo/1.0/direct",
+                &query,
+                &location,
+                "geocoding",
+            )
+            .await?;
+        let mut names: Vec<String> = Vec::new();
+        for place in places {
+            let name = place.to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+
+    fn geocoding_query(&self, city: &str) -> Vec<(&'static str, String)> {
+        let q = match &self.country {
+            Some(country) => format!("{},{}", city, country),
+            None => city.to_string(),
+        };
+        vec![("q", q), ("limit", GEOCODING_LIMIT.to_string())]
+    }
+
+    /// The URL, API key redacted, of the first request that fetching the
+    /// weather (or with `forecast`, the forecast) for `location` would send.
+    /// For a city that is the geocoding lookup.
+    pub fn request_url(&self, location: &Location, forecast: bool) -> Result<String> {
+        let (path, query) = match location {
+            Location::City(city) => ("geo/1.0/direct", self.geocoding_query(city)),
+            Location::Zip { .. } | Location::CityId(_) if self.api == Api::OneCall => {
+                return Err(one_call_location_error())
+            }
+            _ if self.api == Api::OneCall => {
+                let mut query = self.weather_query(location);
+                query.push(("exclude", "minutely,alerts".to_string()));
+                ("data/3.0/onecall", query)
+            }
+            _ if forecast => ("data/2.5/forecast", self.weather_query(location)),
+            _ => ("data/2.5/weather", self.weather_query(location)),
+        };
+        let request = self
+            .http
+            .get(format!("{}/{}", self.base_url, path))
+            .query(&query)
+            .query(&[("appid", &self.api_key)])
+            .build()
+            .map_err(|e| Error::Config(format!("Invalid request: {}", e.without_url())))?;
+        Ok(redact_api_key(request.url()))
+    }
+
+    /// Resolves `city` to its coordinates and canonical name. When several
+    /// places match, a note listing them is printed to stderr and the best
+    /// match is used.
+    pub async fn geocode(&self, city: &str) -> Result<(f64, f64, String)> {
+        let matches = self.geocode_all(city).await?;
+        let best = matches
+            .first()
+            .ok_or_else(|| Error::NotFound(format!("City '{}' not found", city)))?;
+
+        if matches.len() > 1 && self.country.is_none() {
+            let candidates: Vec<String> = matches.iter().map(|m| m.to_string()).collect();
+            eprintln!(
+                "Note: '{}' matches several places ({}); showing {}. Use --country to narrow it down.",
+                city,
+                candidates.join("; "),
+                best
+            );
+        }
+
+        Ok((best.lat, best.lon, best.to_string()))
+    }
+
+    /// Fetches `path` under `base_url` with the `query` parameters straight
+    /// from the API and returns the body of a successful response untouched.
+    #[tracing::instrument(level = "debug", skip_all, fields(%what, %path))]
+    async fn fetch_text(
+        &self,
+        base_url: &str,
+        path: &str,
+        query: &[(&str, String)],
+        location: &Location,
+        what: &str,
+    ) -> Result<String> {
+        let url = format!("{}/{}", base_url, path);
+
+        let response = self.send_with_retries(&url, query, what).await?;
+        let status = response.status();
+        let headers = response.headers();
+        let retry_after = retry_after(headers);
+        if let Some(remaining) = remaining_calls(headers) {
+            debug!(remaining, "rate limit quota");
+            if self.verbose && remaining <= LOW_QUOTA {
+                eprintln!("{}", low_quota_warning(remaining, self.no_emoji));
+            }
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| self.request_error(what, e))?;
+
+        debug!(%status, bytes = body.len(), "response received");
+        if self.verbose {
+            eprintln!("< {}\n{}", status, body);
+        }
+
+        if !status.is_success() {
+            warn!(%status, "API returned an error");
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                return Err(rate_limited(retry_after));
+            }
+            return Err(status_error(status, location, what, &body));
+        }
+        Ok(body)
+    }
+
+    /// Fetches `path` under `base_url` with the `query` parameters, serving
+    /// from and refreshing the cache when one is configured. Only bodies that
+    /// parse successfully are cached. `location` is only used to word error
+    /// messages.
+    #[tracing::instrument(name = "fetch", level = "debug", skip_all, fields(%what, %location))]
+    async fn fetch_json<T: DeserializeOwned>(
+        &self,
+        base_url: &str,
+        path: &str,
+        query: &[(&str, String)],
+        location: &Location,
+        what: &str,
+    ) -> Result<T> {
+        let params: Vec<String> = query.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        let cache_key = format!("{}-{}", what, params.join("&"));
+        if self.offline {
+            let body = self
+                .cache
+                .as_ref()
+                .and_then(|c| c.get_stale(&cache_key))
+                .ok_or_else(|| {
+                    Error::Network(format!("No cached {} data for {}", what, location))
+                })?;
+            debug!("offline, serving the cached response");
+            if self.verbose {
+                eprintln!("< (cached {} response)\n{}", what, body);
+            }
+            return parse(&body, what);
+        }
+        let cached = self.cache.as_ref().filter(|_| !self.refresh);
+        if let Some(body) = cached.and_then(|c| c.get(&cache_key)) {
+            if let Ok(parsed) = serde_json::from_str(&body) {
+                debug!("cache hit");
+                if self.verbose {
+                    eprintln!("< (cached {} response)\n{}", what, body);
+                }
+                return Ok(parsed);
+            }
+        }
+
+        let body = self
+            .fetch_text(base_url, path, query, location, what)
+            .await?;
+        let parsed = parse(&body, what)?;
+
+        if let Some(cache) = &self.cache {
+            cache.put(&cache_key, &body);
+        }
+
+        Ok(parsed)
+    }
+
+    /// Sends a GET request, retrying with exponential backoff on connection
+    /// problems and 5xx responses. Client errors (4xx) are returned as-is
+    /// since repeating them won't help.
+    async fn send_with_retries(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+        what: &str,
+    ) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let request = self
+                .http
+                .get(url)
+                .query(query)
+                .query(&[("appid", &self.api_key)])
+                .build()
+                .map_err(|e| self.request_error(what, e))?;
+            debug!(url = %redact_api_key(request.url()), attempt, "sending request");
+            if self.verbose {
+                eprintln!("> GET {}", redact_api_key(request.url()));
+            }
+            let result = self.http.execute(request).await;
+            let retryable = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !retryable || attempt >= self.retries {
+                return result.map_err(|e| self.request_error(what, e));
+            }
+            let delay = RETRY_BASE_DELAY.saturating_mul(2u32.saturating_pow(attempt));
+            warn!(
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                "transient failure, retrying"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn request_error(&self, what: &str, e: reqwest::Error) -> Error {
+        Error::from_request(e, what, self.timeout)
+    }
+}
+
+/// Parses a response body, as its own span so slow or failing parses stand
+/// out in the logs.
+fn parse<T: DeserializeOwned>(body: &str, what: &str) -> Result<T> {
+    tracing::debug_span!("parse", what).in_scope(|| {
+        serde_json::from_str(body).map_err(|e| {
+            warn!(error = %e, "response didn't parse");
+            Error::Api(format!("Failed to parse {} data: {}", what, e))
+        })
+    })
+}
+
+/// Renders `url` with the `appid` parameter masked so it is safe to print.
+fn redact_api_key(url: &reqwest::Url) -> String {
+    let mut redacted = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| {
+            let v = if k == "appid" { "REDACTED".into() } else { v };
+            (k.into_owned(), v.into_owned())
+        })
+        .collect();
+    redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    redacted.to_string()
+}
+
+fn one_call_location_error() -> Error {
+    Error::Config(
+        "--api one-call needs a city name or coordinates, not a ZIP code or city ID".to_string(),
+    )
+}
+
+fn is_unsubscribed(error: &Error) -> bool {
+    matches!(error, Error::Config(message) if message == ONE_CALL_UNSUBSCRIBED)
+}
+
+fn note_fallback() {
+    warn!("no One Call subscription, falling back to the 2.5 API");
+    eprintln!(
+        "Note: {}; falling back to the 2.5 API (drop --api one-call to skip this)",
+        ONE_CALL_UNSUBSCRIBED
+    );
+}
+
+/// The parts of a full One Call response the reports use. Like the 2.5
+/// endpoints it is requested in Kelvin and m/s.
+#[derive(Deserialize)]
+struct OneCallForecast {
+    lat: f64,
+    lon: f64,
+    #[serde(default)]
+    timezone_offset: i32,
+    current: OneCallConditions,
+    #[serde(default)]
+    hourly: Vec<OneCallHour>,
+    #[serde(default)]
+    daily: Vec<OneCallDay>,
+}
+
+#[derive(Deserialize)]
+struct OneCallConditions {
+    dt: i64,
+    #[serde(default)]
+    sunrise: i64,
+    #[serde(default)]
+    sunset: i64,
+    temp: f64,
+    feels_like: f64,
+    pressure: i32,
+    humidity: i32,
+    clouds: i32,
+    visibility: Option<i32>,
+    wind_speed: f64,
+    wind_deg: Option<f64>,
+    wind_gust: Option<f64>,
+    #[serde(default)]
+    weather: Vec<Weather>,
+    rain: Option<Precipitation>,
+    snow: Option<Precipitation>,
+}
+
+#[derive(Deserialize)]
+struct OneCallHour {
+    dt: i64,
+    temp: f64,
+    feels_like: f64,
+    pressure: i32,
+    humidity: i32,
+    #[serde(default)]
+    weather: Vec<Weather>,
+    pop: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct OneCallDay {
+    dt: i64,
+    temp: OneCallDayTemp,
+    feels_like: OneCallDayFeelsLike,
+    pressure: i32,
+    humidity: i32,
+    #[serde(default)]
+    weather: Vec<Weather>,
+    pop: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct OneCallDayTemp {
+    day: f64,
+    min: f64,
+    max: f64,
+}
+
+#[derive(Deserialize)]
+struct OneCallDayFeelsLike {
+    day: f64,
+}
+
+/// One Call steps through the forecast hourly; the views are written for the
+/// 3-hour steps of the 2.5 forecast.
+const ONE_CALL_HOUR_STEP: usize = 3;
+
+impl OneCallForecast {
+    /// Today's range comes from the daily forecast, since the current
+    /// conditions only carry a single reading.
+    fn into_weather(self, location: &Location) -> WeatherResponse {
+        let current = self.current;
+        let (temp_min, temp_max) = self
+            .daily
+            .first()
+            .map(|day| (day.temp.min, day.temp.max))
+            .unwrap_or((current.temp, current.temp));
+        WeatherResponse {
+            main: Main {
+                temp: current.temp,
+                temp_max,
+                temp_min,
+                feels_like: current.feels_like,
+                humidity: current.humidity,
+                pressure: current.pressure,
+            },
+            weather: current.weather,
+            // One Call doesn't name the place; callers that geocoded a city
+            // put its name in
+            name: location.to_string(),
+            wind: Wind {
+                speed: current.wind_speed,
+                deg: current.wind_deg,
+                gust: current.wind_gust,
+            },
+            sys: Sys {
+                sunrise: current.sunrise,
+                sunset: current.sunset,
+            },
+            coord: Coord {
+                lat: self.lat,
+                lon: self.lon,
+            },
+            dt: current.dt,
+            clouds: Clouds {
+                all: current.clouds,
+            },
+            rain: current.rain,
+            snow: current.snow,
+            visibility: current.visibility,
+            timezone: self.timezone_offset,
+        }
+    }
+
+    /// Every third hour of the 48-hour forecast, then one entry per day once
+    /// those run out, so the daily summary still covers the week.
+    fn into_forecast(self, location: &Location) -> ForecastResponse {
+        let mut list: Vec<ForecastEntry> = self
+            .hourly
+            .into_iter()
+            .step_by(ONE_CALL_HOUR_STEP)
+            .map(|hour| ForecastEntry {
+                dt: hour.dt,
+                main: Main {
+                    temp: hour.temp,
+                    temp_max: hour.temp,
+                    temp_min: hour.temp,
+                    feels_like: hour.feels_like,
+                    humidity: hour.humidity,
+                    pressure: hour.pressure,
+                },
+                weather: hour.weather,
+                pop: hour.pop,
+            })
+            .collect();
+        let last_hour = list.last().map_or(i64::MIN, |entry| entry.dt);
+        list.extend(
+            self.daily
+                .into_iter()
+                .filter(|day| day.dt > last_hour)
+                .map(|day| ForecastEntry {
+                    dt: day.dt,
+                    main: Main {
+                        temp: day.temp.day,
+                        temp_max: day.temp.max,
+                        temp_min: day.temp.min,
+                        feels_like: day.feels_like.day,
+                        humidity: day.humidity,
+                        pressure: day.pressure,
+                    },
+                    weather: day.weather,
+                    pop: day.pop,
+                }),
+        );
+        ForecastResponse {
+            list,
+            city: ForecastCity {
+                name: location.to_string(),
+                timezone: self.timezone_offset,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OneCallResponse {
+    current: OneCallCurrent,
+}
+
+#[derive(Deserialize)]
+struct OneCallCurrent {
+    uvi: f64,
+}
+
+/// Statistics for one day of the year from the Statistical Weather API.
+#[derive(Deserialize)]
+struct DayAggregateResponse {
+    result: DayAggregate,
+}
+
+#[derive(Deserialize)]
+struct DayAggregate {
+    temp: TemperatureStatistics,
+}
+
+/// Only the one figure used is parsed; the API also sends records,
+/// percentiles and the spread. Kelvin, like the rest of the API.
+#[derive(Deserialize)]
+struct TemperatureStatistics {
+    mean: f64,
+}
+
+#[derive(Deserialize)]
+struct AirPollutionResponse {
+    list: Vec<AirPollutionReading>,
+}
+
+#[derive(Deserialize)]
+struct AirPollutionReading {
+    main: AirPollutionIndex,
+    components: AirPollutionComponents,
+}
+
+#[derive(Deserialize)]
+struct AirPollutionIndex {
+    aqi: u8,
+}
+
+#[derive(Deserialize)]
+struct AirPollutionComponents {
+    pm2_5: f64,
+    pm10: f64,
+}
+
+/// Error payload OpenWeatherMap sends alongside non-2xx responses.
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+/// Calls left in the current rate-limit window, from whichever of the usual
+/// headers the server sent.
+fn remaining_calls(headers: &HeaderMap) -> Option<u64> {
+    ["x-ratelimit-remaining", "ratelimit-remaining"]
+        .iter()
+        .find_map(|name| headers.get(*name)?.to_str().ok()?.trim().parse().ok())
+}
+
+/// Seconds to wait before trying again, from a `Retry-After` header given
+/// either as seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(seconds);
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.timestamp() - Utc::now().timestamp()).max(0) as u64)
+}
+
+fn low_quota_warning(remaining: u64, no_emoji: bool) -> String {
+    let icon = if no_emoji { "" } else { "⚠️  " };
+    format!("{}{} API calls remaining this minute", icon, remaining)
+}
+
+fn rate_limited(retry_after: Option<u64>) -> Error {
+    Error::Api(match retry_after {
+        Some(seconds) => format!("Rate limit exceeded, try again in {}s", seconds),
+        None => "Rate limit exceeded, try again later".to_string(),
+    })
+}
+
+/// Maps an unsuccessful HTTP status to a message that says what to do about it.
+fn status_error(status: StatusCode, location: &Location, what: &str, body: &str) -> Error {
+    match status {
+        // Free keys are valid but aren't allowed to call One Call
+        StatusCode::UNAUTHORIZED if what == "UV index" => Error::Config(
+            "The UV index needs a One Call API 3.0 subscription, \
+             which the free OpenWeatherMap plan doesn't include (try --provider open-meteo)"
+                .to_string(),
+        ),
+        StatusCode::UNAUTHORIZED if what == "One Call" => {
+            Error::Config(ONE_CALL_UNSUBSCRIBED.to_string())
+        }
+        StatusCode::UNAUTHORIZED if what == "seasonal average" => Error::Config(
+            "The seasonal average needs a Statistical Weather API subscription, \
+             which the free OpenWeatherMap plan doesn't include"
+                .to_string(),
+        ),
+        StatusCode::NOT_FOUND => Error::NotFound(match location {
+            Location::City(city) => format!("City '{}' not found", city),
+            _ => format!("No weather data found for {}", location),
+        }),
+        StatusCode::UNAUTHORIZED => {
+            Error::Config("Invalid API key; check WEATHER_API_KEY".to_string())
+        }
+        StatusCode::TOO_MANY_REQUESTS => rate_limited(None),
+        _ => {
+            let message = match serde_json::from_str::<ApiError>(body) {
+                Ok(error) => format!("API returned {}: {}", status, error.message),
+                Err(_) => format!("API returned {}", status),
+            };
+            // Still failing after the retries, so treat it like an outage
+            if status.is_server_error() {
+                Error::Network(message)
+            } else {
+                Error::Api(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn mock_client(server: &MockServer) -> WeatherClient {
+        WeatherClient::new("test-key".to_string(), DEFAULT_TIMEOUT)
+            .unwrap()
+            .with_base_url(server.uri())
+            .with_retries(0)
+    }
+
+    #[test]
+    fn parse_base_url_accepts_http_roots_only() {
+        assert_eq!(
+            parse_base_url("https://owm.proxy.internal/").unwrap(),
+            "https://owm.proxy.internal"
+        );
+        assert_eq!(
+            parse_base_url("http://localhost:8080/owm").unwrap(),
+            "http://localhost:8080/owm"
+        );
+        for bad in [
+            "",
+            "api.openweathermap.org",
+            "ftp://mirror.example",
+            "http://",
+        ] {
+            assert!(
+                matches!(parse_base_url(bad), Err(Error::Config(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_weather_parses_a_successful_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/weather"))
+            .and(query_param("q", "London"))
+            .and(query_param("units", "standard"))
+            .and(query_param("appid", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{
+                    "main": {"temp": 285.15, "temp_max": 287.0, "temp_min": 283.0,
+                             "feels_like": 284.0, "humidity": 70, "pressure": 1012},
+                    "weather": [{"description": "light rain", "main": "Rain"}],
+                    "name": "London",
+                    "wind": {"speed": 4.1, "deg": 240},
+                    "sys": {"sunrise": 1700000000, "sunset": 1700030000},
+                    "coord": {"lon": -0.1257, "lat": 51.5085},
+                    "visibility": 10000,
+                    "clouds": {"all": 90},
+                    "timezone": 0
+                }"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        let response = client
+            .fetch_weather(&Location::City("London".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(response.name, "London");
+        assert_eq!(response.main.humidity, 70);
+        assert_eq!(response.weather[0].main, "Rain");
+    }
+
+    #[tokio::test]
+    async fn offline_serves_expired_cache_entries_only() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/weather"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{
+                    "main": {"temp": 285.15, "temp_max": 287.0, "temp_min": 283.0,
+                             "feels_like": 284.0, "humidity": 70, "pressure": 1012},
+                    "weather": [{"description": "light rain", "main": "Rain"}],
+                    "name": "London",
+                    "wind": {"speed": 4.1, "deg": 240},
+                    "sys": {"sunrise": 1700000000, "sunset": 1700030000},
+                    "coord": {"lon": -0.1257, "lat": 51.5085},
+                    "clouds": {"all": 90}
+                }"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        // A zero TTL means the entry is already expired once written
+        let cache = Cache::at(dir.path().to_path_buf(), Duration::ZERO);
+        let london = Location::City("London".to_string());
+        let client = mock_client(&server).with_cache(Some(cache));
+        client.fetch_weather(&london).await.unwrap();
+
+        let offline = client.with_offline(true);
+        let response = offline.fetch_weather(&london).await.unwrap();
+        assert_eq!(response.name, "London");
+        let error = offline
+            .fetch_weather(&Location::City("Paris".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Network(_)));
+    }
+
+    #[tokio::test]
+    async fn refresh_skips_fresh_cache_entries_but_stores_the_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/weather"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{
+                    "main": {"temp": 285.15, "temp_max": 287.0, "temp_min": 283.0,
+                             "feels_like": 284.0, "humidity": 70, "pressure": 1012},
+                    "weather": [{"description": "light rain", "main": "Rain"}],
+                    "name": "London",
+                    "wind": {"speed": 4.1, "deg": 240},
+                    "sys": {"sunrise": 1700000000, "sunset": 1700030000},
+                    "coord": {"lon": -0.1257, "lat": 51.5085},
+                    "clouds": {"all": 90}
+                }"#,
+            ))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::at(dir.path().to_path_buf(), Duration::from_secs(600));
+        let london = Location::City("London".to_string());
+        let refreshing = mock_client(&server)
+            .with_cache(Some(cache))
+            .with_refresh(true);
+        refreshing.fetch_weather(&london).await.unwrap();
+        refreshing.fetch_weather(&london).await.unwrap();
+
+        // The refreshed entry is served from the cache without a third request
+        let response = refreshing
+            .with_refresh(false)
+            .fetch_weather(&london)
+            .await
+            .unwrap();
+        assert_eq!(response.name, "London");
+    }
+
+    #[tokio::test]
+    async fn api_key_stays_out_of_errors_and_debug_output() {
+        // Nothing listens on port 1, so the request fails to connect
+        let client = WeatherClient::new("secret-key".to_string(), DEFAULT_TIMEOUT)
+            .unwrap()
+            .with_base_url("http://127.0.0.1:1")
+            .with_retries(0);
+        let error = client
+            .fetch_weather(&Location::City("London".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Network(_)));
+        assert!(
+            error
+                .to_string()
+                .starts_with("Couldn't connect to 127.0.0.1"),
+            "{}",
+            error
+        );
+        assert!(!error.to_string().contains("secret-key"), "{}", error);
+        assert!(!format!("{:?}", client).contains("secret-key"));
+    }
+
+    #[tokio::test]
+    async fn a_slow_server_is_reported_as_a_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let client = WeatherClient::new("test-key".to_string(), Duration::from_millis(100))
+            .unwrap()
+            .with_base_url(server.uri())
+            .with_retries(0);
+        let error = client
+            .fetch_weather(&Location::City("London".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Network(_)));
+        assert!(
+            error.to_string().starts_with("Request timed out"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_identify_the_tool_in_the_user_agent() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("user-agent", USER_AGENT))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"list": [], "city": {"name": "London"}}"#),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        mock_client(&server)
+            .fetch_forecast(&Location::City("London".to_string()))
+            .await
+            .unwrap();
+        assert!(USER_AGENT.starts_with("weather-cli/"));
+    }
+
+    #[tokio::test]
+    async fn forecast_requests_kelvin() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/forecast"))
+            .and(query_param("units", "standard"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"list": [], "city": {"name": "London"}}"#),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let forecast = mock_client(&server)
+            .fetch_forecast(&Location::City("London".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(forecast.city.name, "London");
+    }
+
+    #[tokio::test]
+    async fn not_found_suggests_the_top_places_from_owm_geocoding() {
+        let server = MockServer::start().await;
+        for (q, body) in [
+            ("London,ZZ", "[]"),
+            (
+                "London",
+                r#"[{"name": "London", "lat": 51.51, "lon": -0.13, "country": "GB"},
+                    {"name": "London", "lat": 42.98, "lon": -81.25, "country": "CA", "state": "Ontario"},
+                    {"name": "London", "lat": 37.13, "lon": -84.08, "country": "US", "state": "Kentucky"},
+                    {"name": "London", "lat": 39.89, "lon": -83.45, "country": "US", "state": "Ohio"}]"#,
+            ),
+            // Only exact names match, so a typo gets nothing to suggest
+            ("Londn", "[]"),
+        ] {
+            Mock::given(method("GET"))
+                .and(path("/geo/1.0/direct"))
+                .and(query_param("q", q))
+                .respond_with(ResponseTemplate::new(200).set_body_string(body))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = mock_client(&server);
+        let location = Location::City("London,ZZ".to_string());
+        let error = crate::WeatherProvider::fetch(&client, &location)
+            .await
+            .unwrap_err();
+        let error = crate::did_you_mean(&client, &location, error).await;
+        assert_eq!(error.exit_code(), 3);
+        assert_eq!(
+            error.to_string(),
+            "City 'London,ZZ' not found. Did you mean: London, GB? \
+             London, Ontario, CA? London, Kentucky, US?"
+        );
+
+        // Asking again for the same name would only find nothing again
+        let location = Location::City("Londn".to_string());
+        let error = crate::WeatherProvider::fetch(&client, &location)
+            .await
+            .unwrap_err();
+        let error = crate::did_you_mean(&client, &location, error).await;
+        assert_eq!(error.to_string(), "City 'Londn' not found");
+    }
+
+    #[tokio::test]
+    async fn geocode_percent_encodes_non_ascii_city_names() {
+        let server = MockServer::start().await;
+        for (city, body) in [
+            (
+                "Zürich",
+                r#"[{"name": "Zürich", "lat": 47.37, "lon": 8.54, "country": "CH"}]"#,
+            ),
+            (
+                "北京",
+                r#"[{"name": "Beijing", "lat": 39.9, "lon": 116.4, "country": "CN"}]"#,
+            ),
+        ] {
+            Mock::given(method("GET"))
+                .and(path("/geo/1.0/direct"))
+                .and(query_param("q", city))
+                .respond_with(ResponseTemplate::new(200).set_body_string(body))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = mock_client(&server);
+        let (_, _, name) = client.geocode("Zürich").await.unwrap();
+        assert_eq!(name, "Zürich, CH");
+        let (lat, lon, name) = client.geocode("北京").await.unwrap();
+        assert_eq!(name, "Beijing, CN");
+        assert!((lat - 39.9).abs() < 1e-9 && (lon - 116.4).abs() < 1e-9);
+
+        // Sent as UTF-8 percent-encoding, not raw bytes
+        let queries: Vec<String> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter_map(|request| request.url.query().map(str::to_string))
+            .collect();
+        assert!(queries[0].starts_with("q=Z%C3%BCrich&"));
+        assert!(queries[1].starts_with("q=%E5%8C%97%E4%BA%AC&"));
+    }
+
+    #[tokio::test]
+    async fn seasonal_average_asks_for_the_day_of_the_year() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/aggregated/day"))
+            .and(query_param("month", "3"))
+            .and(query_param("day", "14"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"cod": 200, "result": {"month": 3, "day": 14,
+                    "temp": {"record_min": 270.1, "mean": 281.4, "st_dev": 2.6}}}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let mean = mock_client(&server)
+            .fetch_seasonal_average(51.5, -0.1, date)
+            .await
+            .unwrap();
+        assert!((mean - 281.4).abs() < 1e-9);
+
+        let location = Location::Coordinates { lat: 1.0, lon: 2.0 };
+        let error = status_error(StatusCode::UNAUTHORIZED, &location, "seasonal average", "");
+        assert!(error.to_string().contains("Statistical Weather API"));
+    }
+
+    #[tokio::test]
+    async fn one_call_maps_into_the_weather_and_forecast() {
+        let server = MockServer::start().await;
+        let hourly: Vec<String> = (0..48)
+            .map(|h| {
+                format!(
+                    r#"{{"dt": {}, "temp": {}, "feels_like": 280.0, "pressure": 1010,
+                        "humidity": 60, "weather": [{{"main": "Clouds", "description": "few clouds"}}],
+                        "pop": 0.1}}"#,
+                    1_700_000_000 + h * 3600,
+                    280.0 + h as f64 / 10.0
+                )
+            })
+            .collect();
+        let daily: Vec<String> = (0..8)
+            .map(|d| {
+                format!(
+                    r#"{{"dt": {}, "temp": {{"day": 282.0, "min": 276.0, "max": 285.0}},
+                        "feels_like": {{"day": 281.0}}, "pressure": 1012, "humidity": 55,
+                        "weather": [{{"main": "Rain", "description": "light rain"}}], "pop": 0.6}}"#,
+                    1_700_000_000 + d * 86_400 + 43_200
+                )
+            })
+            .collect();
+        let body = format!(
+            r#"{{
+                "lat": 47.6, "lon": -122.33, "timezone_offset": -28800,
+                "current": {{"dt": 1700000000, "sunrise": 1699975000, "sunset": 1700009000,
+                             "temp": 281.0, "feels_like": 279.5, "pressure": 1011,
+                             "humidity": 80, "clouds": 75, "visibility": 10000,
+                             "wind_speed": 3.2, "wind_deg": 200,
+                             "weather": [{{"main": "Clouds", "description": "broken clouds"}}],
+                             "rain": {{"1h": 0.4}}, "uvi": 1.2}},
+                "hourly": [{}],
+                "daily": [{}]
+            }}"#,
+            hourly.join(","),
+            daily.join(",")
+        );
+        Mock::given(method("GET"))
+            .and(path("/data/3.0/onecall"))
+            .and(query_param("lat", "47.6"))
+            .and(query_param("exclude", "minutely,alerts"))
+            .and(query_param("units", "standard"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server).with_api(Api::OneCall);
+        let location = Location::Coordinates {
+            lat: 47.6,
+            lon: -122.33,
+        };
+        let weather = client.fetch_weather(&location).await.unwrap();
+        assert_eq!(weather.main.temp, 281.0);
+        assert_eq!(
+            (weather.main.temp_min, weather.main.temp_max),
+            (276.0, 285.0)
+        );
+        assert_eq!(weather.weather[0].description, "broken clouds");
+        assert_eq!(weather.rain.unwrap().one_hour, Some(0.4));
+        assert_eq!(weather.timezone, -28800);
+
+        let forecast = client.fetch_forecast(&location).await.unwrap();
+        // 16 three-hourly steps over 48 hours, then the days after them
+        assert_eq!(forecast.list[1].dt - forecast.list[0].dt, 3 * 3600);
+        assert_eq!(forecast.list.len(), 16 + 6);
+        assert!(forecast.list.windows(2).all(|w| w[0].dt < w[1].dt));
+        assert_eq!(forecast.list.last().unwrap().main.temp_min, 276.0);
+    }
+
+    #[tokio::test]
+    async fn one_call_without_a_subscription_falls_back_to_2_5() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/3.0/onecall"))
+            .respond_with(ResponseTemplate::new(401).set_body_string(
+                r#"{"cod": 401, "message": "Please note that using One Call 3.0 requires a separate subscription"}"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/weather"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{
+                    "main": {"temp": 285.15, "temp_max": 287.0, "temp_min": 283.0,
+                             "feels_like": 284.0, "humidity": 70, "pressure": 1012},
+                    "weather": [{"description": "light rain", "main": "Rain"}],
+                    "name": "Shuzenji",
+                    "wind": {"speed": 4.1},
+                    "sys": {"sunrise": 1700000000, "sunset": 1700030000},
+                    "coord": {"lon": 139.0, "lat": 35.0},
+                    "clouds": {"all": 90}
+                }"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server).with_api(Api::OneCall);
+        let weather = client
+            .fetch_weather(&Location::Coordinates {
+                lat: 35.0,
+                lon: 139.0,
+            })
+            .await
+            .unwrap();
+        assert_eq!(weather.name, "Shuzenji");
+    }
+
+    #[tokio::test]
+    async fn fetch_weather_raw_returns_the_body_untouched() {
+        let server = MockServer::start().await;
+        let body = r#"{"name": "London", "new_field": [1, 2, 3]}"#;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/weather"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let raw = mock_client(&server)
+            .fetch_weather_raw(&Location::City("London".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(raw, body);
+    }
+
+    #[tokio::test]
+    async fn fetch_weather_encodes_multi_word_city_names() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/weather"))
+            .and(query_param("q", "São Paulo & Co"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        let error = client
+            .fetch_weather(&Location::City("São Paulo & Co".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "City 'São Paulo & Co' not found");
+    }
+
+    #[tokio::test]
+    async fn fetch_weather_reports_unknown_city() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/weather"))
+            .respond_with(
+                ResponseTemplate::new(404)
+                    .set_body_string(r#"{"cod":"404","message":"city not found"}"#),
+            )
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        let error = client
+            .fetch_weather(&Location::City("Atlantis".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "City 'Atlantis' not found");
+    }
+
+    #[tokio::test]
+    async fn fetch_weather_rejects_a_malformed_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data/2.5/weather"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"name": "London"}"#))
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        let error = client
+            .fetch_weather(&Location::City("London".to_string()))
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Failed to parse weather data"),
+            "unexpected error: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn status_error_maps_common_codes() {
+        let city = Location::City("Atlantis".to_string());
+        assert_eq!(
+            status_error(StatusCode::NOT_FOUND, &city, "weather", ""),
+            Error::NotFound("City 'Atlantis' not found".to_string())
+        );
+        assert_eq!(
+            status_error(StatusCode::UNAUTHORIZED, &city, "weather", ""),
+            Error::Config("Invalid API key; check WEATHER_API_KEY".to_string())
+        );
+        assert_eq!(
+            status_error(StatusCode::TOO_MANY_REQUESTS, &city, "weather", ""),
+            Error::Api("Rate limit exceeded, try again later".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn rate_limit_errors_say_how_long_to_wait() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("Retry-After", "30")
+                    .set_body_string(
+                        r#"{"cod": 429, "message": "Your account is temporary blocked"}"#,
+                    ),
+            )
+            .mount(&server)
+            .await;
+
+        let error = mock_client(&server)
+            .fetch_forecast(&Location::City("London".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error,
+            Error::Api("Rate limit exceeded, try again in 30s".to_string())
+        );
+    }
+
+    #[test]
+    fn rate_limit_headers_are_read() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(remaining_calls(&headers), None);
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert("X-RateLimit-Remaining", "8".parse().unwrap());
+        assert_eq!(remaining_calls(&headers), Some(8));
+
+        headers.insert(
+            RETRY_AFTER,
+            "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap(),
+        );
+        // A date in the past means it's fine to go again right away
+        assert_eq!(retry_after(&headers), Some(0));
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(120));
+
+        assert_eq!(
+            low_quota_warning(8, false),
+            "⚠️  8 API calls remaining this minute"
+        );
+        assert_eq!(
+            low_quota_warning(8, true),
+            "8 API calls remaining this minute"
+        );
+    }
+
+    #[test]
+    fn request_url_is_the_first_request_with_the_key_redacted() {
+        let client = WeatherClient::new("secret".to_string(), DEFAULT_TIMEOUT)
+            .unwrap()
+            .with_base_url("http://owm.test".to_string());
+        let coordinates = Location::Coordinates {
+            lat: 47.6,
+            lon: -122.3,
+        };
+
+        let url = client.request_url(&coordinates, true).unwrap();
+        assert!(url.starts_with("http://owm.test/data/2.5/forecast?lat=47.6&lon=-122.3"));
+        assert!(url.ends_with("appid=REDACTED"));
+        assert!(!url.contains("secret"));
+
+        let url = client
+            .request_url(&Location::City("Seattle".to_string()), false)
+            .unwrap();
+        assert!(url.starts_with("http://owm.test/geo/1.0/direct?q=Seattle"));
+
+        let one_call = client.with_api(Api::OneCall);
+        assert!(one_call
+            .request_url(&coordinates, false)
+            .unwrap()
+            .contains("/data/3.0/onecall?"));
+        assert!(one_call
+            .request_url(&Location::CityId(2643743), false)
+            .is_err());
+    }
+
+    #[test]
+    fn redact_api_key_masks_only_the_key() {
+        let url = reqwest::Url::parse(
+            "https://api.openweathermap.org/data/2.5/weather?q=S%C3%A3o+Paulo&appid=secret",
+        )
+        .unwrap();
+        let redacted = redact_api_key(&url);
+        assert!(!redacted.contains("secret"));
+        assert_eq!(
+            redacted,
+            "https://api.openweathermap.org/data/2.5/weather?q=S%C3%A3o+Paulo&appid=REDACTED"
+        );
+    }
+
+    #[test]
+    fn status_error_explains_one_call_subscription() {
+        let location = Location::Coordinates { lat: 1.0, lon: 2.0 };
+        let error = status_error(StatusCode::UNAUTHORIZED, &location, "UV index", "");
+        assert!(matches!(error, Error::Config(_)));
+        assert!(error.to_string().contains("One Call API 3.0 subscription"));
+    }
+
+    #[test]
+    fn geo_match_display_includes_state_when_present() {
+        let body = r#"[
+            {"name": "Springfield", "lat": 39.8, "lon": -89.6, "country": "US", "state": "Illinois"},
+            {"name": "Springfield", "lat": -43.3, "lon": 171.9, "country": "NZ"}
+        ]"#;
+        let matches: Vec<GeoMatch> = serde_json::from_str(body).unwrap();
+        assert_eq!(matches[0].to_string(), "Springfield, Illinois, US");
+        assert_eq!(matches[1].to_string(), "Springfield, NZ");
+    }
+
+    #[test]
+    fn status_error_uses_api_message_for_other_codes() {
+        let city = Location::City("London".to_string());
+        let body = r#"{"cod":"400","message":"wrong latitude"}"#;
+        assert_eq!(
+            status_error(StatusCode::BAD_REQUEST, &city, "weather", body),
+            Error::Api("API returned 400 Bad Request: wrong latitude".to_string())
+        );
+        assert_eq!(
+            status_error(StatusCode::BAD_GATEWAY, &city, "weather", "<html>"),
+            Error::Network("API returned 502 Bad Gateway".to_string())
+        );
+    }
+}
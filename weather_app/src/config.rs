@@ -0,0 +1,176 @@
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Environment variable naming the city used when none is given, ahead of
+/// `default_location` in the config file.
+pub const DEFAULT_CITY_ENV: &str = "WEATHER_DEFAULT_CITY";
+
+/// Settings read from `config.toml` in the user's config directory
+/// (`~/.config/weather/config.toml` on Linux).
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct Config {
+    pub api_key: Option<String>,
+    /// Replaces the OpenWeatherMap API root, e.g. for a proxy or mirror
+    pub base_url: Option<String>,
+    /// City used when none is given on the command line
+    pub default_location: Option<String>,
+    /// Named cities, recalled by passing the alias in place of a city
+    #[serde(default)]
+    pub favorites: BTreeMap<String, String>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("weather").join("config.toml"))
+    }
+
+    /// Loads the config file, treating a missing file as an empty config.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|e| Error::Config(format!("Failed to parse {}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(Error::Config(format!(
+                "Failed to read {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// Writes the config file, creating its directory if needed.
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Self::path()
+            .ok_or_else(|| Error::Config("Could not determine the config directory".to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| Error::Other(format!("Failed to create {}: {}", dir.display(), e)))?;
+        }
+        let contents = toml::to_string(self)?;
+        fs::write(&path, contents)
+            .map_err(|e| Error::Other(format!("Failed to write {}: {}", path.display(), e)))?;
+        Ok(path)
+    }
+
+    /// City used when none is given on the command line:
+    /// `WEATHER_DEFAULT_CITY`, then `default_location`.
+    pub fn default_city(&self) -> Option<String> {
+        self.pick_default_city(env::var(DEFAULT_CITY_ENV).ok())
+    }
+
+    /// A blank variable counts as unset, so `WEATHER_DEFAULT_CITY=` falls
+    /// back to the config file.
+    fn pick_default_city(&self, env: Option<String>) -> Option<String> {
+        env.map(|city| city.trim().to_string())
+            .filter(|city| !city.is_empty())
+            .or_else(|| self.default_location.clone())
+    }
+
+    /// Expands a favorite's alias to its city, passing anything else through.
+    pub fn resolve_city(&self, name: &str) -> String {
+        self.favorites
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+}
+
+/// Picks the API key from, in order: the `--api-key` flag, the
+/// `WEATHER_API_KEY` environment variable, then the config file.
+pub fn resolve_api_key(flag: Option<String>, config: &Config) -> Result<String> {
+    pick_api_key(flag, env::var("WEATHER_API_KEY").ok(), config)
+}
+
+/// A blank key, e.g. from `export WEATHER_API_KEY=`, counts as missing so it
+/// fails here instead of as a 401 from the API.
+fn pick_api_key(flag: Option<String>, env: Option<String>, config: &Config) -> Result<String> {
+    let present = |key: &String| !key.trim().is_empty();
+    flag.filter(present)
+        .or_else(|| env.filter(present))
+        .or_else(|| config.api_key.clone().filter(present))
+        .ok_or_else(|| {
+            Error::Config(
+                "Please set the WEATHER_API_KEY environment variable, pass --api-key, \
+                 or add api_key to the config file"
+                    .to_string(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn favorites_round_trip_and_resolve() {
+        let config: Config = toml::from_str(
+            r#"
+            default_location = "Seattle"
+
+            [favorites]
+            home = "Seattle"
+            work = "Bellevue"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.default_location.as_deref(), Some("Seattle"));
+        assert_eq!(config.resolve_city("work"), "Bellevue");
+        assert_eq!(config.resolve_city("Paris"), "Paris");
+
+        let reparsed: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(reparsed.favorites, config.favorites);
+    }
+
+    #[test]
+    fn blank_api_keys_count_as_missing() {
+        let config = Config {
+            api_key: Some("from-config".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            pick_api_key(None, Some(String::new()), &config).unwrap(),
+            "from-config"
+        );
+        assert_eq!(
+            pick_api_key(Some("key".to_string()), Some("  ".to_string()), &config).unwrap(),
+            "key"
+        );
+
+        let error = pick_api_key(None, Some(String::new()), &Config::default()).unwrap_err();
+        assert!(matches!(&error, Error::Config(message) if message.contains("WEATHER_API_KEY")));
+    }
+
+    #[test]
+    fn env_default_city_beats_the_config_file() {
+        let config = Config {
+            default_location: Some("Seattle".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config
+                .pick_default_city(Some(" Tokyo ".to_string()))
+                .as_deref(),
+            Some("Tokyo")
+        );
+        assert_eq!(
+            config.pick_default_city(Some(String::new())).as_deref(),
+            Some("Seattle")
+        );
+        assert_eq!(config.pick_default_city(None).as_deref(), Some("Seattle"));
+        assert_eq!(Config::default().pick_default_city(None), None);
+    }
+
+    #[test]
+    fn favorites_are_optional() {
+        let config: Config = toml::from_str(r#"api_key = "abc""#).unwrap();
+        assert!(config.favorites.is_empty());
+        assert!(config.default_location.is_none());
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Everything that can go wrong, grouped by what a calling script would do
+/// about it. Each group exits with its own status code:
+///
+/// | Code | Variant    | Meaning                                              |
+/// |------|------------|------------------------------------------------------|
+/// | 1    | `Other`    | Anything not covered below (I/O, internal errors)    |
+/// | 2    | `Config`   | Bad arguments, config file or API key                |
+/// | 3    | `NotFound` | The location doesn't exist                           |
+/// | 4    | `Network`  | The API couldn't be reached or timed out             |
+/// | 5    | `Api`      | The API answered, but with an error or unusable data |
+/// | 6    | `Alert`    | The weather crossed an `--alert-above/below` limit   |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Other(String),
+    Config(String),
+    NotFound(String),
+    Network(String),
+    Api(String),
+    Alert(String),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Other(_) => 1,
+            Error::Config(_) => 2,
+            Error::NotFound(_) => 3,
+            Error::Network(_) => 4,
+            Error::Api(_) => 5,
+            Error::Alert(_) => 6,
+        }
+    }
+
+    /// Same category as `self`, with a different message.
+    pub fn with_message(&self, message: String) -> Self {
+        match self {
+            Error::Other(_) => Error::Other(message),
+            Error::Config(_) => Error::Config(message),
+            Error::NotFound(_) => Error::NotFound(message),
+            Error::Network(_) => Error::Network(message),
+            Error::Api(_) => Error::Api(message),
+            Error::Alert(_) => Error::Alert(message),
+        }
+    }
+
+    /// Words a failed HTTP request by what went wrong, so a timeout, a server
+    /// that can't be reached and a malformed URL each read differently.
+    /// reqwest puts the full request URL, API key included, in its messages,
+    /// so only the host is kept.
+    pub(crate) fn from_request(e: reqwest::Error, what: &str, timeout: Duration) -> Self {
+        let host = e
+            .url()
+            .and_then(|url| url.host_str())
+            .unwrap_or("the server")
+            .to_string();
+        let e = e.without_url();
+        let cause = root_cause(&e);
+        if e.is_timeout() {
+            Error::Network(format!(
+                "Request timed out after {}s waiting for {} ({} data); try a longer --timeout",
+                timeout.as_secs(),
+                host,
+                what
+            ))
+        } else if e.is_connect() {
+            Error::Network(format!(
+                "Couldn't connect to {} for {} data ({}); check your network connection",
+                host, what, cause
+            ))
+        } else if e.is_builder() {
+            Error::Config(format!("Invalid {} request URL: {}", what, cause))
+        } else if e.is_request() {
+            Error::Network(format!(
+                "Failed to send the {} request to {}: {}",
+                what, host, cause
+            ))
+        } else {
+            Error::Network(format!(
+                "Failed to read {} data from {}: {}",
+                what, host, cause
+            ))
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            Error::Other(message)
+            | Error::Config(message)
+            | Error::NotFound(message)
+            | Error::Network(message)
+            | Error::Api(message)
+            | Error::Alert(message) => message,
+        }
+    }
+}
+
+/// The innermost error, which for reqwest is the one that says what actually
+/// happened (e.g. "Connection refused") rather than "error sending request".
+fn root_cause(error: &dyn std::error::Error) -> String {
+    let mut cause = error;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    cause.to_string()
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for ExitCode {
+    fn from(error: Error) -> Self {
+        ExitCode::from(error.exit_code())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Other(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Other(error.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(error: toml::ser::Error) -> Self {
+        Error::Other(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_category_has_its_own_exit_code() {
+        let codes: Vec<u8> = [
+            Error::Other(String::new()),
+            Error::Config(String::new()),
+            Error::NotFound(String::new()),
+            Error::Network(String::new()),
+            Error::Api(String::new()),
+            Error::Alert(String::new()),
+        ]
+        .iter()
+        .map(Error::exit_code)
+        .collect();
+        assert_eq!(codes, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn with_message_keeps_the_category() {
+        let error = Error::NotFound("City 'Atlantis' not found".to_string());
+        let summary = error.with_message("Failed to get weather for 1 of 1 location(s)".into());
+        assert_eq!(summary.exit_code(), 3);
+        assert_eq!(
+            summary.to_string(),
+            "Failed to get weather for 1 of 1 location(s)"
+        );
+    }
+}
@@ -0,0 +1,3441 @@
+pub mod cache;
+pub mod client;
+pub mod config;
+pub mod error;
+pub mod open_meteo;
+pub mod provider;
+pub mod trend;
+pub mod units;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeDelta, Utc};
+use clap::ValueEnum;
+use colored::{Color, ColoredString, Colorize};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::io::{self, Write};
+
+pub use cache::Cache;
+pub use client::{Api, WeatherClient};
+pub use config::Config;
+pub use error::Error;
+pub use open_meteo::OpenMeteoClient;
+pub use provider::{did_you_mean, Provider, WeatherProvider, WeatherReport};
+pub use units::{DisplayUnits, TempUnit, Units, WindUnit};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Where to look up the weather.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    City(String),
+    Coordinates {
+        lat: f64,
+        lon: f64,
+    },
+    Zip {
+        code: String,
+        country: String,
+    },
+    /// OpenWeatherMap's own numeric ID for a city, which is never ambiguous
+    CityId(u64),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::City(city) => write!(f, "{}", city),
+            Location::Coordinates { lat, lon } => write!(f, "{}, {}", lat, lon),
+            Location::Zip { code, country } => write!(f, "{}, {}", code, country),
+            Location::CityId(id) => write!(f, "city ID {}", id),
+        }
+    }
+}
+
+impl Location {
+    pub(crate) fn query(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Location::City(city) => vec![("q", city.clone())],
+            Location::Coordinates { lat, lon } => {
+                vec![("lat", lat.to_string()), ("lon", lon.to_string())]
+            }
+            Location::Zip { code, country } => vec![("zip", format!("{},{}", code, country))],
+            Location::CityId(id) => vec![("id", id.to_string())],
+        }
+    }
+
+    /// Trims a city name, rejecting one that is empty or only whitespace.
+    pub fn parse_city(input: &str) -> Result<Self> {
+        let city = input.trim();
+        if city.is_empty() {
+            return Err(Error::Config("City name cannot be empty".to_string()));
+        }
+        Ok(Location::City(city.to_string()))
+    }
+
+    /// Parses a `<code>[,country]` postal code, defaulting the country to `us`.
+    pub fn parse_zip(input: &str) -> Result<Self> {
+        let (code, country) = match input.split_once(',') {
+            Some((code, country)) => (code.trim(), country.trim()),
+            None => (input.trim(), "us"),
+        };
+
+        let valid_code = !code.is_empty()
+            && code.len() <= 10
+            && code
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-');
+        if !valid_code {
+            return Err(Error::Config(format!("Invalid ZIP/postal code '{}'", code)));
+        }
+        Ok(Location::Zip {
+            code: code.to_string(),
+            country: parse_country_code(country)?.to_ascii_lowercase(),
+        })
+    }
+}
+
+/// Checks that `input` is a two-letter ISO 3166 country code and returns it
+/// in upper case.
+pub fn parse_country_code(input: &str) -> Result<String> {
+    let code = input.trim();
+    if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(Error::Config(format!(
+            "Invalid country code '{}'; expected two letters like 'us'",
+            code
+        )));
+    }
+    Ok(code.to_ascii_uppercase())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WeatherResponse {
+    pub main: Main,
+    pub weather: Vec<Weather>,
+    pub name: String,
+    pub wind: Wind,
+    pub sys: Sys,
+    pub coord: Coord,
+    /// When the observation was made, as a Unix timestamp
+    #[serde(default)]
+    pub dt: i64,
+    pub clouds: Clouds,
+    pub rain: Option<Precipitation>,
+    pub snow: Option<Precipitation>,
+    pub visibility: Option<i32>,
+    /// Shift in seconds from UTC for the location
+    #[serde(default)]
+    pub timezone: i32,
+}
+
+/// Cloud cover as a percentage of the sky.
+#[derive(Deserialize, Debug)]
+pub struct Clouds {
+    pub all: i32,
+}
+
+/// Rain or snow volume in mm; the API only sends the periods it has data for.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Precipitation {
+    #[serde(rename = "1h", skip_serializing_if = "Option::is_none")]
+    pub one_hour: Option<f64>,
+    #[serde(rename = "3h", skip_serializing_if = "Option::is_none")]
+    pub three_hours: Option<f64>,
+}
+
+impl Precipitation {
+    /// The most recent volume available along with the period it covers.
+    pub fn latest(&self) -> Option<(f64, &'static str)> {
+        self.one_hour
+            .map(|mm| (mm, "1h"))
+            .or(self.three_hours.map(|mm| (mm, "3h")))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Coord {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Main {
+    pub temp: f64,
+    pub temp_max: f64,
+    pub temp_min: f64,
+    pub feels_like: f64,
+    pub humidity: i32,
+    pub pressure: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Weather {
+    pub description: String,
+    pub main: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Wind {
+    pub speed: f64,
+    pub deg: Option<f64>,
+    pub gust: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Sys {
+    pub sunrise: i64,
+    pub sunset: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ForecastResponse {
+    pub list: Vec<ForecastEntry>,
+    pub city: ForecastCity,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ForecastEntry {
+    pub dt: i64,
+    pub main: Main,
+    pub weather: Vec<Weather>,
+    /// Probability of precipitation, from 0.0 to 1.0
+    pub pop: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ForecastCity {
+    pub name: String,
+    /// Shift in seconds from UTC for the location
+    #[serde(default)]
+    pub timezone: i32,
+}
+
+/// One calendar day of the forecast, aggregated from the 3-hour entries.
+#[derive(Serialize, Debug)]
+pub struct ForecastDay {
+    pub date: NaiveDate,
+    pub low: f64,
+    pub high: f64,
+    pub weather: String,
+    /// Highest probability of precipitation across the day, from 0.0 to 1.0
+    pub pop: Option<f64>,
+}
+
+/// Air pollution reading; `aqi` is OpenWeatherMap's 1 (good) to 5 (very
+/// poor) index and particulate matter is in µg/m³.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AirQuality {
+    pub aqi: u8,
+    pub pm2_5: f64,
+    pub pm10: f64,
+}
+
+/// One 3-hour step of the `--hourly` forecast.
+#[derive(Serialize, Debug)]
+pub struct ForecastHour {
+    pub dt: i64,
+    /// Local time at the location, `HH:MM`
+    pub time: String,
+    pub temp: f64,
+    pub weather: String,
+    pub pop: Option<f64>,
+}
+
+/// Machine-readable sun times printed by `suntimes --json`.
+#[derive(Serialize, Debug)]
+pub struct SunTimes {
+    pub location: String,
+    pub sunrise: i64,
+    pub sunset: i64,
+    /// Local time at the location, `HH:MM`
+    pub sunrise_local: String,
+    /// Local time at the location, `HH:MM`
+    pub sunset_local: String,
+    pub day_length_minutes: i64,
+}
+
+impl SunTimes {
+    pub fn new(report: &WeatherReport) -> Self {
+        SunTimes {
+            location: report.location.clone(),
+            sunrise: report.sunrise,
+            sunset: report.sunset,
+            sunrise_local: format_timestamp(report.sunrise, report.timezone, TimeFormat::H24),
+            sunset_local: format_timestamp(report.sunset, report.timezone, TimeFormat::H24),
+            day_length_minutes: (report.sunset - report.sunrise).max(0) / 60,
+        }
+    }
+}
+
+/// Version of the `--json` layout. Bump it whenever a field of
+/// [`WeatherSummary`] is renamed, removed or changes type, so consumers can
+/// tell a layout they don't understand from the one they were written for.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What `--json` prints for each location: the summary tagged with the
+/// [`SCHEMA_VERSION`] it follows.
+#[derive(Serialize, Debug)]
+pub struct JsonEnvelope {
+    pub schema_version: u32,
+    pub weather: WeatherSummary,
+}
+
+impl From<WeatherSummary> for JsonEnvelope {
+    fn from(weather: WeatherSummary) -> Self {
+        JsonEnvelope {
+            schema_version: SCHEMA_VERSION,
+            weather,
+        }
+    }
+}
+
+/// Machine-readable report printed by `--json`, inside a [`JsonEnvelope`].
+#[derive(Serialize, Debug)]
+pub struct WeatherSummary {
+    pub location: String,
+    pub description: String,
+    pub temperature_unit: &'static str,
+    pub temperature: f64,
+    pub feels_like: f64,
+    pub high: f64,
+    pub low: f64,
+    pub humidity: i32,
+    pub pressure_hpa: i32,
+    pub visibility_m: Option<i32>,
+    pub cloud_cover: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rain: Option<Precipitation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snow: Option<Precipitation>,
+    pub wind_speed: f64,
+    pub wind_gust: Option<f64>,
+    pub wind_speed_unit: &'static str,
+    pub wind_direction: Option<&'static str>,
+    pub sunrise: i64,
+    pub sunset: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub air_quality: Option<AirQuality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uv_index: Option<f64>,
+    /// Degrees above (or, negative, below) the seasonal average
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anomaly: Option<f64>,
+    /// Whether this is an old cached reading served because the API couldn't
+    /// be reached
+    pub stale: bool,
+}
+
+pub fn kelvin_to_celsius(kelvin: f64) -> f64 {
+    kelvin - 273.15
+}
+
+pub fn kelvin_to_fahrenheit(kelvin: f64) -> f64 {
+    (kelvin - 273.15) * 9.0 / 5.0 + 32.0
+}
+
+pub fn meters_per_second_to_kmh(mps: f64) -> f64 {
+    mps * 3.6
+}
+
+pub fn meters_per_second_to_mph(mps: f64) -> f64 {
+    mps * 3600.0 / 1609.344
+}
+
+pub fn meters_per_second_to_knots(mps: f64) -> f64 {
+    mps * 3600.0 / 1852.0
+}
+
+pub fn hpa_to_inhg(hpa: f64) -> f64 {
+    hpa * 0.02953
+}
+
+pub fn meters_to_miles(meters: f64) -> f64 {
+    meters / 1609.344
+}
+
+/// Heat index only means something in hot weather.
+pub const HEAT_INDEX_MIN_F: f64 = 80.0;
+
+/// Wind chill is only defined for cool temperatures with some wind.
+pub const WIND_CHILL_MAX_F: f64 = 50.0;
+pub const WIND_CHILL_MIN_MPH: f64 = 3.0;
+
+/// Heat index or wind chill this close to the air temperature (1°C) isn't
+/// worth a line of its own.
+pub const APPARENT_TEMP_MIN_DIFF_F: f64 = 1.8;
+
+pub fn fahrenheit_to_kelvin(fahrenheit: f64) -> f64 {
+    (fahrenheit - 32.0) * 5.0 / 9.0 + 273.15
+}
+
+/// NWS heat index in Fahrenheit using the Rothfusz regression, including
+/// the NWS adjustments for very dry and very humid air.
+/// See https://www.wpc.ncep.noaa.gov/html/heatindex_equation.shtml
+pub fn heat_index(temp_f: f64, humidity: f64) -> f64 {
+    let (t, rh) = (temp_f, humidity);
+
+    // Steadman's simple formula is accurate enough below ~80°F
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    if (simple + t) / 2.0 < HEAT_INDEX_MIN_F {
+        return simple;
+    }
+
+    let mut hi = -42.379 + 2.04901523 * t + 10.14333127 * rh
+        - 0.22475541 * t * rh
+        - 0.00683783 * t * t
+        - 0.05481717 * rh * rh
+        + 0.00122874 * t * t * rh
+        + 0.00085282 * t * rh * rh
+        - 0.00000199 * t * t * rh * rh;
+
+    if rh < 13.0 && (80.0..=112.0).contains(&t) {
+        hi -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+    } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+        hi += (rh - 85.0) / 10.0 * ((87.0 - t) / 5.0);
+    }
+
+    hi
+}
+
+/// NWS wind chill in Fahrenheit.
+/// See https://www.weather.gov/media/epz/wxcalc/windChill.pdf
+pub fn wind_chill(temp_f: f64, wind_mph: f64) -> f64 {
+    let v = wind_mph.powf(0.16);
+    35.74 + 0.6215 * temp_f - 35.75 * v + 0.4275 * temp_f * v
+}
+
+/// Heat index or wind chill in Fahrenheit, when either applies to the
+/// current conditions and is noticeably different from the air temperature.
+pub fn apparent_temperature(temp_f: f64, humidity: f64, wind_mph: f64) -> Option<f64> {
+    let apparent = if temp_f >= HEAT_INDEX_MIN_F {
+        heat_index(temp_f, humidity)
+    } else if temp_f <= WIND_CHILL_MAX_F && wind_mph > WIND_CHILL_MIN_MPH {
+        wind_chill(temp_f, wind_mph)
+    } else {
+        return None;
+    };
+    ((apparent - temp_f).abs() > APPARENT_TEMP_MIN_DIFF_F).then_some(apparent)
+}
+
+/// Feels-like temperature in Celsius above which heat is dangerous (the
+/// NWS "danger" heat index band starts at 103°F).
+pub const EXTREME_HEAT_C: f64 = 39.4;
+
+/// Feels-like temperature in Celsius below which exposed skin can get
+/// frostbite within 30 minutes.
+pub const EXTREME_COLD_C: f64 = -27.0;
+
+/// Warning to show when a feels-like temperature, given in Kelvin like every
+/// stored reading, is dangerously hot or cold.
+pub fn extreme_warning(feels_like_kelvin: f64) -> Option<&'static str> {
+    let celsius = kelvin_to_celsius(feels_like_kelvin);
+    if celsius >= EXTREME_HEAT_C {
+        Some("Extreme heat — stay hydrated")
+    } else if celsius <= EXTREME_COLD_C {
+        Some("Extreme cold — frostbite risk")
+    } else {
+        None
+    }
+}
+
+/// Alert line for `--alert-above`/`--alert-below` when the current
+/// temperature is beyond either limit. Limits are in the display unit.
+pub fn temperature_alert(
+    report: &WeatherReport,
+    units: DisplayUnits,
+    above: Option<f64>,
+    below: Option<f64>,
+    options: RenderOptions,
+) -> Option<String> {
+    let temp = convert_temp(report.temp, units.temperature);
+    let (direction, limit) = match (above, below) {
+        (Some(limit), _) if temp > limit => ("above", limit),
+        (_, Some(limit)) if temp < limit => ("below", limit),
+        _ => return None,
+    };
+    let symbol = units.temperature.symbol();
+    Some(format!(
+        "{}Alert: {} is {}{}, {} the {}{} limit",
+        options.icon("🚨 "),
+        report.location,
+        format_temp(temp, units.precision),
+        symbol,
+        direction,
+        format_temp(limit, units.precision),
+        symbol
+    ))
+}
+
+/// Dew point in Celsius from the Magnus formula. `humidity` is relative
+/// humidity in percent and must be above zero.
+pub fn dew_point(temp_c: f64, humidity: f64) -> f64 {
+    const A: f64 = 17.62;
+    const B: f64 = 243.12;
+    let gamma = (humidity / 100.0).ln() + A * temp_c / (B + temp_c);
+    B * gamma / (A - gamma)
+}
+
+/// Upper bound (exclusive, in m/s) and description of Beaufort forces 0-11;
+/// anything faster is force 12.
+const BEAUFORT_BANDS: [(f64, &str); 12] = [
+    (0.5, "Calm"),
+    (1.6, "Light air"),
+    (3.4, "Light breeze"),
+    (5.5, "Gentle breeze"),
+    (8.0, "Moderate breeze"),
+    (10.8, "Fresh breeze"),
+    (13.9, "Strong breeze"),
+    (17.2, "Near gale"),
+    (20.8, "Gale"),
+    (24.5, "Strong gale"),
+    (28.5, "Storm"),
+    (32.7, "Violent storm"),
+];
+
+/// Maps a wind speed in m/s to its Beaufort force and description.
+pub fn beaufort_scale(mps: f64) -> (u8, &'static str) {
+    BEAUFORT_BANDS
+        .iter()
+        .position(|(limit, _)| mps < *limit)
+        .map(|force| (force as u8, BEAUFORT_BANDS[force].1))
+        .unwrap_or((12, "Hurricane force"))
+}
+
+pub fn get_wind_direction(degrees: f64) -> &'static str {
+    let directions = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+        "NW", "NNW",
+    ];
+    // Normalize into [0, 360) first so negative or wrapped values from the API
+    // still map onto a compass point, then clamp against float rounding.
+    let normalized = (degrees + 11.25).rem_euclid(360.0);
+    let index = ((normalized / 22.5) as usize).min(directions.len() - 1);
+    directions[index]
+}
+
+/// Arrow for a wind coming from `degrees`, to the nearest of the 8 compass
+/// points. Like on a weather map it points the way the wind blows, so a
+/// northerly wind is `↓`.
+pub fn wind_arrow(degrees: f64) -> char {
+    let arrows = ['↓', '↙', '←', '↖', '↑', '↗', '→', '↘'];
+    let normalized = (degrees + 22.5).rem_euclid(360.0);
+    let index = ((normalized / 45.0) as usize).min(arrows.len() - 1);
+    arrows[index]
+}
+
+/// Multi-line ASCII-art drawing of a condition group, for terminals that
+/// can't show emoji. Every drawing is five lines of at most 14 columns.
+pub fn get_weather_ascii(weather_main: &str) -> &'static str {
+    match weather_main.to_lowercase().as_str() {
+        "clear" => concat!(
+            "    \\   /    \n",
+            "     .-.      \n",
+            "  - (   ) -   \n",
+            "     `-'      \n",
+            "    /   \\    ",
+        ),
+        "clouds" => concat!(
+            "              \n",
+            "     .--.     \n",
+            "  .-(    ).   \n",
+            " (___.__)__)  \n",
+            "              ",
+        ),
+        "rain" => concat!(
+            "     .-.      \n",
+            "    (   ).    \n",
+            "   (___(__)   \n",
+            "    ' ' ' '   \n",
+            "   ' ' ' '    ",
+        ),
+        "snow" => concat!(
+            "     .-.      \n",
+            "    (   ).    \n",
+            "   (___(__)   \n",
+            "    *  *  *   \n",
+            "   *  *  *    ",
+        ),
+        "thunderstorm" => concat!(
+            "     .-.      \n",
+            "    (   ).    \n",
+            "   (___(__)   \n",
+            "    /_  /_    \n",
+            "     /   /    ",
+        ),
+        "drizzle" => concat!(
+            "  \\  /        \n",
+            " _ /\"\".-.     \n",
+            "   \\_(   ).   \n",
+            "   /(___(__)  \n",
+            "     ' ' ' '  ",
+        ),
+        "mist" | "fog" => concat!(
+            "              \n",
+            " _ - _ - _ -  \n",
+            "  _ - _ - _   \n",
+            " _ - _ - _ -  \n",
+            "              ",
+        ),
+        _ => concat!(
+            "     .-.      \n",
+            "    (   )     \n",
+            "      /       \n",
+            "     '        \n",
+            "     .        ",
+        ),
+    }
+}
+
+/// Which icons mark the weather condition, chosen with `--emoji-style`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmojiStyle {
+    /// Color emoji
+    #[default]
+    Emoji,
+    /// Nerd Font weather glyphs
+    #[value(name = "nerdfont")]
+    NerdFont,
+    /// No condition icons
+    None,
+}
+
+/// Icon for an OpenWeatherMap condition group in the given style; empty
+/// with [`EmojiStyle::None`].
+pub fn get_weather_emoji(weather_main: &str, style: EmojiStyle) -> &'static str {
+    let condition = weather_main.to_lowercase();
+    match style {
+        EmojiStyle::Emoji => match condition.as_str() {
+            "clear" => "☀️",
+            "clouds" => "☁️",
+            "rain" => "🌧️",
+            "snow" => "❄️",
+            "thunderstorm" => "⛈️",
+            "drizzle" => "🌦️",
+            "mist" | "fog" => "🌫️",
+            _ => "🌡️",
+        },
+        // nf-weather-day_sunny, cloudy, rain, snow, thunderstorm, sprinkle,
+        // fog and thermometer
+        EmojiStyle::NerdFont => match condition.as_str() {
+            "clear" => "\u{e30d}",
+            "clouds" => "\u{e312}",
+            "rain" => "\u{e318}",
+            "snow" => "\u{e31a}",
+            "thunderstorm" => "\u{e31d}",
+            "drizzle" => "\u{e31b}",
+            "mist" | "fog" => "\u{e313}",
+            _ => "\u{e350}",
+        },
+        EmojiStyle::None => "",
+    }
+}
+
+pub fn convert_temp(kelvin: f64, unit: TempUnit) -> f64 {
+    unit.convert(kelvin)
+}
+
+/// Formats a temperature with `precision` decimal places and an explicit
+/// leading minus sign for values below zero. The sign follows the rounded
+/// value, so something like -0.04 prints as "0.0" rather than "-0.0".
+pub fn format_temp(value: f64, precision: usize) -> String {
+    let digits = format!("{:.*}", precision, value.abs());
+    let rounds_to_zero = digits.chars().all(|c| c == '0' || c == '.');
+    if value < 0.0 && !rounds_to_zero {
+        format!("-{}", digits)
+    } else {
+        digits
+    }
+}
+
+/// Color for a temperature, from blue at freezing up to red when it's hot.
+/// The bands are in Celsius so the colors don't change with the display unit.
+pub fn temp_color(celsius: f64) -> Color {
+    if celsius <= 0.0 {
+        Color::BrightBlue
+    } else if celsius < 10.0 {
+        Color::BrightCyan
+    } else if celsius < 20.0 {
+        Color::BrightGreen
+    } else if celsius < 25.0 {
+        Color::BrightYellow
+    } else if celsius < 30.0 {
+        Color::Yellow
+    } else {
+        Color::BrightRed
+    }
+}
+
+/// Color for relative humidity by comfort: yellow when dry (under 30%),
+/// green when comfortable (30–50%), magenta when humid (over 60%), and the
+/// usual cyan in between.
+pub fn humidity_color(pct: i32) -> Color {
+    match pct {
+        ..=29 => Color::Yellow,
+        30..=50 => Color::BrightGreen,
+        51..=60 => Color::BrightCyan,
+        _ => Color::BrightMagenta,
+    }
+}
+
+/// A temperature already converted to the display unit, formatted and colored.
+fn colored_temp(value: f64, units: DisplayUnits, theme: Theme) -> ColoredString {
+    format_temp(value, units.precision)
+        .paint(theme.scale(temp_color(units.temperature.to_celsius(value))))
+}
+
+/// Background the colors are picked for, chosen with `--color-theme`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorTheme {
+    /// Bright colors for dark backgrounds
+    #[default]
+    Dark,
+    /// Darker colors for light backgrounds
+    Light,
+    /// No colors, only bold and dim text
+    Mono,
+}
+
+/// Colors for each kind of value in the reports. A `None` leaves the text
+/// in the terminal's own foreground color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Place names
+    pub location: Option<Color>,
+    /// Pressure, visibility, clouds, precipitation and air quality
+    pub reading: Option<Color>,
+    /// Wind speed, gusts and direction
+    pub wind: Option<Color>,
+    /// The condition, sun times, moon phase and UV index
+    pub highlight: Option<Color>,
+    /// Notices such as a cached reading being shown
+    pub warning: Option<Color>,
+    /// Extreme-weather warnings and alerts
+    pub danger: Option<Color>,
+    /// Color temperatures and humidity by value
+    pub scales: bool,
+    /// Use the bright variants of the scale colors
+    pub bright: bool,
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        location: Some(Color::BrightBlue),
+        reading: Some(Color::BrightCyan),
+        wind: Some(Color::BrightMagenta),
+        highlight: Some(Color::BrightYellow),
+        warning: Some(Color::Yellow),
+        danger: Some(Color::BrightRed),
+        scales: true,
+        bright: true,
+    };
+
+    // Yellow washes out on white, so highlights and warnings move to other hues
+    pub const LIGHT: Theme = Theme {
+        location: Some(Color::Blue),
+        reading: Some(Color::Cyan),
+        wind: Some(Color::Magenta),
+        highlight: Some(Color::Green),
+        warning: Some(Color::Red),
+        danger: Some(Color::Red),
+        scales: true,
+        bright: false,
+    };
+
+    pub const MONO: Theme = Theme {
+        location: None,
+        reading: None,
+        wind: None,
+        highlight: None,
+        warning: None,
+        danger: None,
+        scales: false,
+        bright: false,
+    };
+
+    /// How a color from [`temp_color`], [`humidity_color`] or [`uv_color`]
+    /// is shown.
+    pub fn scale(&self, color: Color) -> Option<Color> {
+        if !self.scales {
+            return None;
+        }
+        if self.bright {
+            return Some(color);
+        }
+        Some(match color {
+            Color::BrightBlack => Color::Black,
+            Color::BrightRed => Color::Red,
+            Color::BrightGreen => Color::Green,
+            Color::BrightYellow => Color::Yellow,
+            Color::BrightBlue => Color::Blue,
+            Color::BrightMagenta => Color::Magenta,
+            Color::BrightCyan => Color::Cyan,
+            Color::BrightWhite => Color::White,
+            other => other,
+        })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DARK
+    }
+}
+
+impl From<ColorTheme> for Theme {
+    fn from(theme: ColorTheme) -> Self {
+        match theme {
+            ColorTheme::Dark => Theme::DARK,
+            ColorTheme::Light => Theme::LIGHT,
+            ColorTheme::Mono => Theme::MONO,
+        }
+    }
+}
+
+/// Like [`Colorize::color`], but leaves the text uncolored for `None`.
+pub trait Paint {
+    fn paint(&self, color: Option<Color>) -> ColoredString;
+}
+
+impl Paint for str {
+    fn paint(&self, color: Option<Color>) -> ColoredString {
+        match color {
+            Some(color) => self.color(color),
+            None => self.normal(),
+        }
+    }
+}
+
+/// Calendar date at a location `offset` seconds from UTC.
+fn local_date(timestamp: i64, offset: i32) -> Option<NaiveDate> {
+    DateTime::from_timestamp(timestamp + i64::from(offset), 0).map(|dt| dt.date_naive())
+}
+
+/// Groups the 3-hour forecast entries by calendar day in the location's
+/// time zone, like [`next_hours`], keeping the min/max temperature and the
+/// most frequent weather condition.
+pub fn summarize_forecast(
+    forecast: &ForecastResponse,
+    days: usize,
+    unit: TempUnit,
+) -> Vec<ForecastDay> {
+    type Day<'a> = (f64, f64, BTreeMap<&'a str, usize>, Option<f64>);
+    let mut grouped: BTreeMap<NaiveDate, Day> = BTreeMap::new();
+
+    for entry in &forecast.list {
+        let Some(date) = local_date(entry.dt, forecast.city.timezone) else {
+            continue;
+        };
+        let day = grouped.entry(date).or_insert((
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            BTreeMap::new(),
+            None,
+        ));
+        day.0 = day.0.min(entry.main.temp_min);
+        day.1 = day.1.max(entry.main.temp_max);
+        if let Some(weather) = entry.weather.first() {
+            *day.2.entry(weather.main.as_str()).or_insert(0) += 1;
+        }
+        if let Some(pop) = entry.pop {
+            day.3 = Some(day.3.map_or(pop, |max: f64| max.max(pop)));
+        }
+    }
+
+    grouped
+        .into_iter()
+        .take(days)
+        .map(|(date, (low, high, conditions, pop))| ForecastDay {
+            date,
+            low: convert_temp(low, unit),
+            high: convert_temp(high, unit),
+            weather: conditions
+                .into_iter()
+                .max_by_key(|(_, count)| *count)
+                .map(|(main, _)| main.to_string())
+                .unwrap_or_default(),
+            pop,
+        })
+        .collect()
+}
+
+/// Standard label for OpenWeatherMap's 1-5 air quality index.
+pub fn aqi_label(aqi: u8) -> &'static str {
+    match aqi {
+        1 => "Good",
+        2 => "Fair",
+        3 => "Moderate",
+        4 => "Poor",
+        5 => "Very Poor",
+        _ => "Unknown",
+    }
+}
+
+/// Standard WHO band for a UV index reading.
+pub fn uv_category(uv_index: f64) -> &'static str {
+    if uv_index < 3.0 {
+        "Low"
+    } else if uv_index < 6.0 {
+        "Moderate"
+    } else if uv_index < 8.0 {
+        "High"
+    } else if uv_index < 11.0 {
+        "Very High"
+    } else {
+        "Extreme"
+    }
+}
+
+/// Orange for the "High" UV band; the terminal palette has none.
+const ORANGE: Color = Color::TrueColor {
+    r: 255,
+    g: 140,
+    b: 0,
+};
+
+/// WHO color for a UV index reading's band: green, yellow, orange, red,
+/// then violet, on the same boundaries as [`uv_category`].
+pub fn uv_color(uv_index: f64) -> Color {
+    if uv_index < 3.0 {
+        Color::BrightGreen
+    } else if uv_index < 6.0 {
+        Color::BrightYellow
+    } else if uv_index < 8.0 {
+        ORANGE
+    } else if uv_index < 11.0 {
+        Color::BrightRed
+    } else {
+        Color::BrightMagenta
+    }
+}
+
+/// Placeholder shown in place of a time the API sent us that can't be parsed.
+pub const INVALID_TIME: &str = "--:--";
+
+/// Clock used for times of day, chosen with `--time-format`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeFormat {
+    /// 12-hour clock with AM/PM, e.g. 07:13 AM
+    #[value(name = "12")]
+    H12,
+    /// 24-hour clock, e.g. 19:13
+    #[default]
+    #[value(name = "24")]
+    H24,
+}
+
+/// Regions whose locales conventionally use the 12-hour clock.
+const TWELVE_HOUR_REGIONS: [&str; 6] = ["US", "CA", "AU", "NZ", "IN", "PH"];
+
+impl TimeFormat {
+    /// strftime pattern for a time of day in this format.
+    pub fn pattern(self) -> &'static str {
+        match self {
+            TimeFormat::H12 => "%I:%M %p",
+            TimeFormat::H24 => "%H:%M",
+        }
+    }
+
+    /// The clock the user's locale (`LC_ALL`, `LC_TIME`, then `LANG`) uses.
+    pub fn from_locale() -> Self {
+        ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map_or(TimeFormat::default(), |locale| Self::for_locale(&locale))
+    }
+
+    /// The clock for a POSIX locale name such as `en_US.UTF-8`.
+    fn for_locale(locale: &str) -> Self {
+        let region = locale
+            .split(['.', '@'])
+            .next()
+            .and_then(|name| name.split_once('_'))
+            .map(|(_, region)| region);
+        match region {
+            Some(region) if TWELVE_HOUR_REGIONS.contains(&region) => TimeFormat::H12,
+            _ => TimeFormat::H24,
+        }
+    }
+}
+
+/// Formats a Unix timestamp as a time of day at the given offset from UTC,
+/// so times show up in the queried location's local time.
+pub fn format_timestamp(timestamp: i64, utc_offset_secs: i32, format: TimeFormat) -> String {
+    let Some(offset) = FixedOffset::east_opt(utc_offset_secs) else {
+        return INVALID_TIME.to_string();
+    };
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(datetime) => datetime
+            .with_timezone(&offset)
+            .format(format.pattern())
+            .to_string(),
+        None => INVALID_TIME.to_string(),
+    }
+}
+
+/// Length of the synodic month (new moon to new moon) in days.
+const SYNODIC_MONTH_DAYS: f64 = 29.530588853;
+
+/// A new moon to count lunations from: 2000-01-06 18:14 UTC.
+const REFERENCE_NEW_MOON: i64 = 947_182_440;
+
+/// Approximates the moon phase at noon UTC on `date` from the mean synodic
+/// cycle, returning its name and emoji. Good to within about a day.
+pub fn moon_phase(date: NaiveDate) -> (&'static str, &'static str) {
+    const PHASES: [(&str, &str); 8] = [
+        ("New Moon", "🌑"),
+        ("Waxing Crescent", "🌒"),
+        ("First Quarter", "🌓"),
+        ("Waxing Gibbous", "🌔"),
+        ("Full Moon", "🌕"),
+        ("Waning Gibbous", "🌖"),
+        ("Last Quarter", "🌗"),
+        ("Waning Crescent", "🌘"),
+    ];
+    let noon = date
+        .and_hms_opt(12, 0, 0)
+        .expect("noon is a valid time")
+        .and_utc()
+        .timestamp();
+    let days = (noon - REFERENCE_NEW_MOON) as f64 / 86_400.0;
+    let cycle = days.rem_euclid(SYNODIC_MONTH_DAYS) / SYNODIC_MONTH_DAYS;
+    // Each phase is centred on its point in the cycle, hence the half step
+    PHASES[((cycle * 8.0 + 0.5) as usize) % 8]
+}
+
+/// Arrow and signed change for a temperature difference given in Kelvin,
+/// e.g. `↑ +1.2°`.
+pub fn format_trend(delta_kelvin: f64, units: DisplayUnits) -> String {
+    let delta = units.temperature.convert_delta(delta_kelvin);
+    if units.rounds_to_zero(delta) {
+        format!("→ {}°", units.number(0.0))
+    } else if delta > 0.0 {
+        format!("↑ +{}°", units.number(delta))
+    } else {
+        format!("↓ -{}°", units.number(delta.abs()))
+    }
+}
+
+/// Pressure given in hPa, in inHg for imperial units.
+pub fn format_pressure(hpa: i32, units: DisplayUnits) -> String {
+    if units.system == Units::Imperial {
+        format!("{:.2} inHg", hpa_to_inhg(hpa as f64))
+    } else {
+        format!("{} hPa", hpa)
+    }
+}
+
+/// Which way the pressure moved since the previous reading, in hPa, with
+/// the change in the pressure's display unit, e.g. `falling ↓ 3 hPa`.
+pub fn format_pressure_trend(delta_hpa: i32, units: DisplayUnits) -> String {
+    let change = if units.system == Units::Imperial {
+        format!("{:.2} inHg", hpa_to_inhg(delta_hpa.abs() as f64))
+    } else {
+        format!("{} hPa", delta_hpa.abs())
+    };
+    match delta_hpa.signum() {
+        1 => format!("rising ↑ {}", change),
+        -1 => format!("falling ↓ {}", change),
+        _ => "steady →".to_string(),
+    }
+}
+
+/// How much warmer or colder it feels than it is, from the difference in
+/// Kelvin, e.g. `-4.0° colder`. `None` when the two round to the same value.
+pub fn format_feels_like_delta(delta_kelvin: f64, units: DisplayUnits) -> Option<String> {
+    let delta = units.temperature.convert_delta(delta_kelvin);
+    if units.rounds_to_zero(delta) {
+        None
+    } else if delta > 0.0 {
+        Some(format!("+{}° warmer", units.number(delta)))
+    } else {
+        Some(format!("-{}° colder", units.number(delta.abs())))
+    }
+}
+
+/// How a temperature difference from the seasonal average, in Kelvin,
+/// reads, e.g. `3.2° above seasonal average`.
+pub fn format_anomaly(delta_kelvin: f64, units: DisplayUnits) -> String {
+    let delta = units.temperature.convert_delta(delta_kelvin);
+    if units.rounds_to_zero(delta) {
+        "right at the seasonal average".to_string()
+    } else if delta > 0.0 {
+        format!("{}° above seasonal average", units.number(delta))
+    } else {
+        format!("{}° below seasonal average", units.number(delta.abs()))
+    }
+}
+
+/// How long ago something happened, in the largest whole unit, e.g.
+/// `47 minutes ago`.
+pub fn format_age(seconds: i64) -> String {
+    let (count, unit) = match seconds.max(0) {
+        s if s < 60 => return "less than a minute ago".to_string(),
+        s if s < 3600 => (s / 60, "minute"),
+        s if s < 86400 => (s / 3600, "hour"),
+        s => (s / 86400, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+/// Formats the time between sunrise and sunset as e.g. `10h 42m`. A sunset
+/// before sunrise (polar regions, bad data) is clamped to zero.
+pub fn format_day_length(sunrise: i64, sunset: i64) -> String {
+    let minutes = (sunset - sunrise).max(0) / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+/// Whether it is day or night at the location at `now`, with how long since
+/// sunrise and until sunset during the day. All three are Unix timestamps,
+/// so the answer holds for the location whatever the user's own time zone.
+pub fn daylight(now: i64, sunrise: i64, sunset: i64, options: RenderOptions) -> String {
+    if sunrise <= now && now < sunset {
+        format!(
+            "{}Daytime — {} since sunrise, {} until sunset",
+            options.icon("☀️  "),
+            format_day_length(sunrise, now),
+            format_day_length(now, sunset)
+        )
+    } else {
+        format!("{}Nighttime", options.icon("🌙 "))
+    }
+}
+
+impl WeatherSummary {
+    pub fn new(report: &WeatherReport, units: DisplayUnits) -> Self {
+        let weather = report.weather.as_ref();
+        let temp_unit = units.temperature;
+        WeatherSummary {
+            location: report.location.clone(),
+            description: weather.map(|w| w.description.clone()).unwrap_or_default(),
+            temperature_unit: temp_unit.code(),
+            temperature: convert_temp(report.temp, temp_unit),
+            feels_like: convert_temp(report.feels_like, temp_unit),
+            high: convert_temp(report.temp_max, temp_unit),
+            low: convert_temp(report.temp_min, temp_unit),
+            humidity: report.humidity,
+            pressure_hpa: report.pressure,
+            visibility_m: report.visibility,
+            cloud_cover: report.clouds,
+            rain: report.rain.clone(),
+            snow: report.snow.clone(),
+            wind_speed: units.wind.convert(report.wind_speed),
+            wind_gust: report.wind_gust.map(|gust| units.wind.convert(gust)),
+            wind_speed_unit: units.wind.symbol(),
+            wind_direction: report.wind_deg.map(get_wind_direction),
+            sunrise: report.sunrise,
+            sunset: report.sunset,
+            air_quality: report.air_quality.clone(),
+            uv_index: report.uv_index,
+            anomaly: report.anomaly.map(|delta| temp_unit.convert_delta(delta)),
+            stale: report.stale,
+        }
+    }
+}
+
+/// Columns assumed when the output isn't a terminal, e.g. when piped.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Width of the terminal stdout is attached to, or [`DEFAULT_WIDTH`].
+pub fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(width), _)| width as usize)
+        .filter(|&width| width > 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Columns `text` takes up on a terminal: its characters, not counting the
+/// ANSI escape sequences that color and style it.
+pub fn visible_width(text: &str) -> usize {
+    let mut escape = false;
+    text.chars()
+        .filter(|&c| {
+            if escape {
+                // Color and style sequences end in a letter, usually `m`
+                escape = !c.is_ascii_alphabetic();
+                false
+            } else {
+                escape = c == '\x1b';
+                !escape
+            }
+        })
+        .count()
+}
+
+/// Shortens `text` to at most `max` visible characters, marking the cut
+/// with `…`.
+pub fn truncate(text: &str, max: usize) -> String {
+    if visible_width(text) <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Breaks `text` into lines of at most `width` visible characters at word
+/// boundaries.
+/// A single word longer than `width` gets a line of its own.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && visible_width(&line) + 1 + visible_width(word) > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Passes everything through to the writer it wraps, dropping the ANSI
+/// escape sequences `colored` adds when `strip` is set. Unlike
+/// `colored::control::set_override`, this only affects the one render.
+pub struct PlainWriter<'a> {
+    out: &'a mut dyn Write,
+    strip: bool,
+    escape: bool,
+}
+
+impl<'a> PlainWriter<'a> {
+    pub fn new(out: &'a mut dyn Write, strip: bool) -> Self {
+        PlainWriter {
+            out,
+            strip,
+            escape: false,
+        }
+    }
+}
+
+impl Write for PlainWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.strip {
+            return self.out.write(buf);
+        }
+        // ESC and the letters that end a sequence are never part of a
+        // multi-byte character, so bytes can be checked one at a time even
+        // when a sequence is split across writes
+        let mut plain = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            if self.escape {
+                self.escape = !byte.is_ascii_alphabetic();
+            } else if byte == 0x1b {
+                self.escape = true;
+            } else {
+                plain.push(byte);
+            }
+        }
+        self.out.write_all(&plain)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// How the human-readable reports are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Columns available; long text is truncated or wrapped to fit
+    pub width: usize,
+    /// Draw the condition as ASCII art above the report instead of an emoji
+    pub ascii: bool,
+    /// Icons used for the weather condition
+    pub emoji: EmojiStyle,
+    /// Leave out every emoji, labels included, so lines start with text
+    pub no_emoji: bool,
+    /// Colors picked for the terminal's background
+    pub theme: Theme,
+    /// Clock for sunrise, sunset and other times of day
+    pub time_format: TimeFormat,
+    /// Keep colors and text styles; off drops them even on a terminal
+    pub color: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            width: DEFAULT_WIDTH,
+            ascii: false,
+            emoji: EmojiStyle::default(),
+            no_emoji: false,
+            theme: Theme::default(),
+            time_format: TimeFormat::default(),
+            color: true,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// `out`, with colors and styles stripped on the way when they're off.
+    pub fn writer<'a>(&self, out: &'a mut dyn Write) -> PlainWriter<'a> {
+        PlainWriter::new(out, !self.color)
+    }
+
+    /// `icon`, which carries its own trailing spacing, or nothing when
+    /// emoji are turned off. Dropping the spacing along with the icon keeps
+    /// every label starting in the first column.
+    pub fn icon<'a>(&self, icon: &'a str) -> &'a str {
+        if self.no_emoji {
+            ""
+        } else {
+            icon
+        }
+    }
+
+    /// Icon for an OpenWeatherMap condition group in the chosen style.
+    pub fn condition_icon(&self, weather_main: &str) -> &'static str {
+        if self.no_emoji {
+            ""
+        } else {
+            get_weather_emoji(weather_main, self.emoji)
+        }
+    }
+}
+
+/// Renders the full report.
+pub fn write_report(
+    out: &mut dyn Write,
+    report: &WeatherReport,
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> io::Result<()> {
+    let out = &mut options.writer(out);
+    let temp = convert_temp(report.temp, units.temperature);
+    let temp_max = convert_temp(report.temp_max, units.temperature);
+    let temp_min = convert_temp(report.temp_min, units.temperature);
+    let feels_like = convert_temp(report.feels_like, units.temperature);
+
+    let temp_unit = units.temperature.symbol();
+    let wind_speed = units.wind.convert(report.wind_speed);
+
+    // The API may send an empty `weather` array; say so rather than leaving a blank
+    let weather_main = report
+        .weather
+        .as_ref()
+        .map(|w| w.main.as_str())
+        .unwrap_or_default();
+    // "☁️  Weather: " takes up the first 13 columns, "Weather: " 9
+    let label_width = if options.no_emoji { 9 } else { 13 };
+    let description = match report.weather.as_ref().map(|w| w.description.as_str()) {
+        Some(description) if !description.is_empty() => {
+            truncate(description, options.width.saturating_sub(label_width))
+                .paint(options.theme.highlight)
+        }
+        _ => "(no weather description available)".dimmed(),
+    };
+
+    writeln!(out, "\n{}", "Current Weather".bold().underline())?;
+    if report.stale {
+        let age = format_age(Utc::now().timestamp() - report.observed_at);
+        writeln!(
+            out,
+            "{}",
+            format!("{}Cached reading from {}", options.icon("⚠️  "), age)
+                .paint(options.theme.warning)
+        )?;
+    }
+    if let Some(warning) = extreme_warning(report.feels_like) {
+        writeln!(
+            out,
+            "{}",
+            format!("{}{}", options.icon("⚠️  "), warning)
+                .paint(options.theme.danger)
+                .bold()
+        )?;
+    }
+    if options.ascii {
+        writeln!(out, "{}", get_weather_ascii(weather_main))?;
+    }
+    writeln!(
+        out,
+        "{}Location: {}",
+        options.icon("🌍 "),
+        report.location.paint(options.theme.location)
+    )?;
+    // The drawing already shows the condition
+    let icon = if options.ascii {
+        ""
+    } else {
+        options.condition_icon(weather_main)
+    };
+    // Keep the gap after the icon, even when there is none, unless emoji
+    // are off altogether
+    writeln!(
+        out,
+        "{}{}Weather: {}",
+        icon,
+        options.icon("  "),
+        description
+    )?;
+    let trend = report
+        .trend
+        .map(|delta| format!(" {}", format_trend(delta, units)))
+        .unwrap_or_default();
+    writeln!(
+        out,
+        "{}Temperature: {}{}{}",
+        options.icon("🌡️  "),
+        colored_temp(temp, units, options.theme),
+        temp_unit,
+        trend.dimmed()
+    )?;
+
+    let feels_like_delta = format_feels_like_delta(report.feels_like - report.temp, units)
+        .map(|delta| format!(" ({})", delta))
+        .unwrap_or_default();
+    writeln!(
+        out,
+        "{}Feels like: {}{}{}",
+        options.icon("🤔 "),
+        colored_temp(feels_like, units, options.theme),
+        temp_unit,
+        feels_like_delta.dimmed()
+    )?;
+    if let Some(anomaly) = report.anomaly {
+        writeln!(
+            out,
+            "{}{}",
+            options.icon("📊 "),
+            format_anomaly(anomaly, units)
+        )?;
+    }
+
+    writeln!(
+        out,
+        "{}Today's High/Low: {}{}/{}{}",
+        options.icon("🌡️  "),
+        colored_temp(temp_max, units, options.theme),
+        temp_unit,
+        colored_temp(temp_min, units, options.theme),
+        temp_unit
+    )?;
+
+    writeln!(
+        out,
+        "{}Humidity: {}%",
+        options.icon("💧 "),
+        report
+            .humidity
+            .to_string()
+            .paint(options.theme.scale(humidity_color(report.humidity)))
+    )?;
+
+    if report.humidity > 0 {
+        let dew_point_c = dew_point(kelvin_to_celsius(report.temp), report.humidity as f64);
+        // Back to Kelvin so it goes through the same unit conversion as the rest
+        let dew_point = convert_temp(dew_point_c + 273.15, units.temperature);
+        writeln!(
+            out,
+            "{}Dew point: {}{}",
+            options.icon("💧 "),
+            colored_temp(dew_point, units, options.theme),
+            temp_unit
+        )?;
+    }
+
+    let temp_f = kelvin_to_fahrenheit(report.temp);
+    let apparent = apparent_temperature(
+        temp_f,
+        report.humidity as f64,
+        meters_per_second_to_mph(report.wind_speed),
+    );
+    if let Some(apparent_f) = apparent {
+        let emoji = if temp_f >= HEAT_INDEX_MIN_F {
+            "🥵 "
+        } else {
+            "🥶 "
+        };
+        let apparent = convert_temp(fahrenheit_to_kelvin(apparent_f), units.temperature);
+        writeln!(
+            out,
+            "{}Apparent temperature: {}{}",
+            options.icon(emoji),
+            colored_temp(apparent, units, options.theme),
+            temp_unit
+        )?;
+    }
+
+    let pressure = format_pressure(report.pressure, units);
+    let pressure_trend = report
+        .pressure_trend
+        .map(|delta| format!(" ({})", format_pressure_trend(delta, units)))
+        .unwrap_or_default();
+    writeln!(
+        out,
+        "{}Pressure: {}{}",
+        options.icon("🔽 "),
+        pressure.paint(options.theme.reading),
+        pressure_trend.dimmed()
+    )?;
+
+    if let Some(visibility) = report.visibility {
+        let visibility = if units.system == Units::Imperial {
+            format!("{} mi", units.number(meters_to_miles(visibility as f64)))
+        } else {
+            format!("{} km", units.number(visibility as f64 / 1000.0))
+        };
+        writeln!(
+            out,
+            "{}Visibility: {}",
+            options.icon("👁️  "),
+            visibility.paint(options.theme.reading)
+        )?;
+    }
+
+    writeln!(
+        out,
+        "{}Cloud cover: {}%",
+        options.icon("☁️  "),
+        report.clouds.to_string().paint(options.theme.reading)
+    )?;
+
+    write_precipitation(out, report, units, options)?;
+
+    // Wind information; the gust phrase is left out when there's no gust
+    let gust = report
+        .wind_gust
+        .map(|gust| {
+            format!(
+                " gusting to {} {}",
+                units
+                    .number(units.wind.convert(gust))
+                    .paint(options.theme.wind),
+                units.wind.symbol()
+            )
+        })
+        .unwrap_or_default();
+    // Only mention the direction when the API reported one
+    let direction = report
+        .wind_deg
+        .map(|deg| {
+            format!(
+                " from {} {}",
+                get_wind_direction(deg).paint(options.theme.wind),
+                wind_arrow(deg)
+            )
+        })
+        .unwrap_or_default();
+    let (force, force_name) = beaufort_scale(report.wind_speed);
+    if force == 0 {
+        writeln!(
+            out,
+            "{}Wind: {}",
+            options.icon("🌪️  "),
+            "calm".paint(options.theme.wind)
+        )?;
+    } else {
+        writeln!(
+            out,
+            "{}Wind: {} {}{}{} (Force {} – {})",
+            options.icon("🌪️  "),
+            units.number(wind_speed).paint(options.theme.wind),
+            units.wind.symbol(),
+            gust,
+            direction,
+            force,
+            force_name
+        )?;
+    }
+
+    write_sun_lines(out, report, options)?;
+    if report.sunrise < report.sunset {
+        writeln!(
+            out,
+            "{}",
+            daylight(
+                Utc::now().timestamp(),
+                report.sunrise,
+                report.sunset,
+                options
+            )
+        )?;
+    }
+
+    // Today's date where the location is, not where the user is
+    let local_today = (Utc::now() + TimeDelta::seconds(report.timezone.into())).date_naive();
+    let (phase, phase_emoji) = moon_phase(local_today);
+    writeln!(
+        out,
+        "{}Moon: {}{}",
+        options.icon("🌙 "),
+        phase.paint(options.theme.highlight),
+        options.icon(&format!(" {}", phase_emoji))
+    )?;
+
+    if let Some(air_quality) = &report.air_quality {
+        writeln!(
+            out,
+            "{}Air quality: {} (AQI {}), PM2.5 {:.0}µg/m³, PM10 {:.0}µg/m³",
+            options.icon("🏭 "),
+            aqi_label(air_quality.aqi).paint(options.theme.reading),
+            air_quality.aqi,
+            air_quality.pm2_5,
+            air_quality.pm10
+        )?;
+    }
+    if let Some(uv_index) = report.uv_index {
+        writeln!(
+            out,
+            "{}UV index: {} ({})",
+            options.icon("🔆 "),
+            format!("{:.0}", uv_index).paint(options.theme.scale(uv_color(uv_index))),
+            uv_category(uv_index)
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Rain and snow over the last hour and the last three hours, whichever the
+/// API sent, or a single line saying there was none.
+fn write_precipitation(
+    out: &mut dyn Write,
+    report: &WeatherReport,
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> io::Result<()> {
+    let out = &mut options.writer(out);
+    let mut any = false;
+    for (emoji, label, precipitation) in [
+        ("🌧️  ", "Rain", &report.rain),
+        ("🌨️  ", "Snow", &report.snow),
+    ] {
+        let amounts: Vec<String> = precipitation
+            .iter()
+            .flat_map(|p| [(p.one_hour, "1h"), (p.three_hours, "3h")])
+            .filter_map(|(mm, period)| {
+                mm.map(|mm| {
+                    format!(
+                        "{} mm (last {})",
+                        units.number(mm).paint(options.theme.reading),
+                        period
+                    )
+                })
+            })
+            .collect();
+        if amounts.is_empty() {
+            continue;
+        }
+        any = true;
+        writeln!(
+            out,
+            "{}{}: {}",
+            options.icon(emoji),
+            label,
+            amounts.join(", ")
+        )?;
+    }
+    if !any {
+        writeln!(out, "{}No precipitation", options.icon("🌂 "))?;
+    }
+    Ok(())
+}
+
+/// Sunrise and sunset in the location's local time, and the day length.
+fn write_sun_lines(
+    out: &mut dyn Write,
+    report: &WeatherReport,
+    options: RenderOptions,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "{}Sunrise: {} (local)",
+        options.icon("🌅 "),
+        format_timestamp(report.sunrise, report.timezone, options.time_format)
+            .paint(options.theme.highlight)
+    )?;
+    writeln!(
+        out,
+        "{}Sunset: {} (local)",
+        options.icon("🌇 "),
+        format_timestamp(report.sunset, report.timezone, options.time_format)
+            .paint(options.theme.highlight)
+    )?;
+    writeln!(
+        out,
+        "{}Day length: {}",
+        options.icon("🕐 "),
+        format_day_length(report.sunrise, report.sunset).paint(options.theme.highlight)
+    )?;
+    Ok(())
+}
+
+/// Renders only the sun times, for the `suntimes` subcommand.
+pub fn write_suntimes(
+    out: &mut dyn Write,
+    report: &WeatherReport,
+    options: RenderOptions,
+) -> io::Result<()> {
+    let out = &mut options.writer(out);
+    writeln!(out, "\n{}", "Sun Times".bold().underline())?;
+    writeln!(
+        out,
+        "{}Location: {}",
+        options.icon("🌍 "),
+        report.location.paint(options.theme.location)
+    )?;
+    write_sun_lines(out, report, options)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Title and body of the `--notify` desktop notification, e.g.
+/// `Weather in London` and `12.3°C, broken clouds`.
+pub fn format_notification(report: &WeatherReport, units: DisplayUnits) -> (String, String) {
+    let temp = format!(
+        "{}{}",
+        format_temp(
+            convert_temp(report.temp, units.temperature),
+            units.precision
+        ),
+        units.temperature.symbol()
+    );
+    let body = match &report.weather {
+        Some(weather) => format!("{}, {}", temp, weather.description),
+        None => temp,
+    };
+    (format!("Weather in {}", report.location), body)
+}
+
+/// Spelled-out name of a compass point from [`get_wind_direction`], e.g.
+/// `northwest` for `NW` and `north-northwest` for `NNW`.
+fn compass_name(point: &str) -> String {
+    let word = |c: char| match c {
+        'N' => "north",
+        'E' => "east",
+        'S' => "south",
+        _ => "west",
+    };
+    let words = |s: &str| s.chars().map(word).collect::<String>();
+    match point.len() {
+        3 => format!("{}-{}", words(&point[..1]), words(&point[1..])),
+        _ => words(point),
+    }
+}
+
+/// How a condition group reads after "It's currently 12°C and", or `None`
+/// for groups without a natural adjective.
+fn condition_adjective(weather_main: &str) -> Option<&'static str> {
+    match weather_main.to_lowercase().as_str() {
+        "clear" => Some("clear"),
+        "clouds" => Some("cloudy"),
+        "rain" => Some("rainy"),
+        "drizzle" => Some("drizzly"),
+        "snow" => Some("snowy"),
+        "thunderstorm" => Some("stormy"),
+        "mist" => Some("misty"),
+        "fog" => Some("foggy"),
+        "haze" => Some("hazy"),
+        "smoke" => Some("smoky"),
+        "dust" | "sand" => Some("dusty"),
+        _ => None,
+    }
+}
+
+/// One plain-English sentence for `--summary`, e.g. `It's currently 12°C and
+/// cloudy in London, feeling like 9°C with a 15 km/h northwest wind.` Parts
+/// the data doesn't support (no condition, a feels-like equal to the
+/// temperature, no wind direction) are left out rather than guessed.
+pub fn format_summary(report: &WeatherReport, units: DisplayUnits) -> String {
+    let temp = |kelvin: f64| {
+        format!(
+            "{}{}",
+            format_temp(convert_temp(kelvin, units.temperature), units.precision),
+            units.temperature.symbol()
+        )
+    };
+    let mut sentence = format!("It's currently {}", temp(report.temp));
+    if let Some(adjective) = report
+        .weather
+        .as_ref()
+        .and_then(|w| condition_adjective(&w.main))
+    {
+        sentence.push_str(&format!(" and {}", adjective));
+    }
+    sentence.push_str(&format!(" in {}", report.location));
+
+    let feels_like = temp(report.feels_like);
+    let feels_different = feels_like != temp(report.temp);
+    if feels_different {
+        sentence.push_str(&format!(", feeling like {}", feels_like));
+    }
+
+    let wind = if beaufort_scale(report.wind_speed).0 == 0 {
+        "calm winds".to_string()
+    } else {
+        let direction = report
+            .wind_deg
+            .map(|deg| format!(" {}", compass_name(get_wind_direction(deg))))
+            .unwrap_or_default();
+        format!(
+            "a {} {}{} wind",
+            units.number(units.wind.convert(report.wind_speed)),
+            units.wind.symbol(),
+            direction
+        )
+    };
+    let joiner = if feels_different { " with" } else { ", with" };
+    format!("{}{} {}.", sentence, joiner, wind)
+}
+
+/// One-line report for `--quiet`, e.g. `London: 12.3°C ☁️`.
+pub fn format_quiet(report: &WeatherReport, units: DisplayUnits, options: RenderOptions) -> String {
+    let weather_main = report
+        .weather
+        .as_ref()
+        .map(|w| w.main.as_str())
+        .unwrap_or_default();
+    format!(
+        "{}: {}{} {}",
+        report.location,
+        format_temp(
+            convert_temp(report.temp, units.temperature),
+            units.precision
+        ),
+        units.temperature.symbol(),
+        options.condition_icon(weather_main)
+    )
+    .trim_end()
+    .to_string()
+}
+
+/// Header row for `--csv`, matching the columns of [`format_csv_row`].
+pub const CSV_HEADER: &str =
+    "city,temp,feels_like,humidity,wind_speed,wind_dir,pressure,conditions";
+
+/// One `--csv` row. Temperatures and wind speed are in the display units and
+/// pressure in hPa; numbers carry no unit so spreadsheets read them as such.
+pub fn format_csv_row(report: &WeatherReport, units: DisplayUnits) -> String {
+    let fields = [
+        report.location.clone(),
+        units.number(convert_temp(report.temp, units.temperature)),
+        units.number(convert_temp(report.feels_like, units.temperature)),
+        report.humidity.to_string(),
+        units.number(units.wind.convert(report.wind_speed)),
+        report
+            .wind_deg
+            .map(get_wind_direction)
+            .unwrap_or_default()
+            .to_string(),
+        report.pressure.to_string(),
+        report
+            .weather
+            .as_ref()
+            .map(|w| w.description.clone())
+            .unwrap_or_default(),
+    ];
+    fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Quotes a CSV field when it holds a comma, quote or line break, doubling
+/// any quotes inside (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Value substituted for a `--format` placeholder, or `None` if `name`
+/// isn't one.
+fn template_value(
+    name: &str,
+    report: &WeatherReport,
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> Option<String> {
+    let temp = |kelvin: f64| {
+        format!(
+            "{}{}",
+            format_temp(convert_temp(kelvin, units.temperature), units.precision),
+            units.temperature.symbol()
+        )
+    };
+    let weather = report.weather.as_ref();
+    let value = match name {
+        "city" => report.location.clone(),
+        "temp" => temp(report.temp),
+        "feels_like" => temp(report.feels_like),
+        "high" => temp(report.temp_max),
+        "low" => temp(report.temp_min),
+        "humidity" => format!("{}%", report.humidity),
+        "pressure" => format_pressure(report.pressure, units),
+        "wind_speed" => format!(
+            "{} {}",
+            units.number(units.wind.convert(report.wind_speed)),
+            units.wind.symbol()
+        ),
+        "wind_dir" => report
+            .wind_deg
+            .map(get_wind_direction)
+            .unwrap_or("-")
+            .to_string(),
+        "description" => weather.map(|w| w.description.clone()).unwrap_or_default(),
+        "emoji" => options
+            .condition_icon(weather.map(|w| w.main.as_str()).unwrap_or_default())
+            .to_string(),
+        "sunrise" => format_timestamp(report.sunrise, report.timezone, options.time_format),
+        "sunset" => format_timestamp(report.sunset, report.timezone, options.time_format),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Fills in a `--format` template such as `"{city}: {temp} {emoji}"`.
+/// Anything in braces that isn't a known placeholder is left as written.
+pub fn format_template(
+    template: &str,
+    report: &WeatherReport,
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> String {
+    let mut formatted = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let placeholder = after
+            .find('}')
+            .and_then(|end| Some((end, template_value(&after[..end], report, units, options)?)));
+        match placeholder {
+            Some((end, value)) => {
+                formatted.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                formatted.push('{');
+                rest = after;
+            }
+        }
+    }
+    formatted.push_str(rest);
+    formatted
+}
+
+/// Written between reports when more than one location is requested.
+pub fn write_separator(out: &mut dyn Write, options: RenderOptions) -> io::Result<()> {
+    let out = &mut options.writer(out);
+    writeln!(out, "{}", "─".repeat(40).dimmed())?;
+    Ok(())
+}
+
+/// The first `count` 3-hour entries of the forecast, temperatures converted
+/// to `unit` and times in the location's local time.
+pub fn next_hours(forecast: &ForecastResponse, count: usize, unit: TempUnit) -> Vec<ForecastHour> {
+    forecast
+        .list
+        .iter()
+        .take(count)
+        .map(|entry| ForecastHour {
+            dt: entry.dt,
+            time: format_timestamp(entry.dt, forecast.city.timezone, TimeFormat::H24),
+            temp: convert_temp(entry.main.temp, unit),
+            weather: entry
+                .weather
+                .first()
+                .map(|w| w.main.clone())
+                .unwrap_or_default(),
+            pop: entry.pop,
+        })
+        .collect()
+}
+
+/// Formats a 0.0-1.0 probability as a whole percentage.
+pub fn format_pop(pop: f64) -> String {
+    format!("{:.0}%", pop * 100.0)
+}
+
+/// The condition icon followed by the usual gap, or nothing at all when
+/// icons are turned off.
+fn icon_column(weather_main: &str, options: RenderOptions) -> String {
+    match options.condition_icon(weather_main) {
+        "" => String::new(),
+        icon => format!("{}  ", icon),
+    }
+}
+
+pub fn write_hourly(
+    out: &mut dyn Write,
+    forecast: &ForecastResponse,
+    hours: &[ForecastHour],
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> io::Result<()> {
+    let out = &mut options.writer(out);
+    writeln!(out, "\n{}", "Next 24 Hours".bold().underline())?;
+    writeln!(
+        out,
+        "{}Location: {}",
+        options.icon("🌍 "),
+        forecast.city.name.paint(options.theme.location)
+    )?;
+    for hour in hours {
+        // Pad before coloring so the escape codes don't break the columns
+        let temp = format!(
+            "{:>8}",
+            format!(
+                "{}{}",
+                format_temp(hour.temp, units.precision),
+                units.temperature.symbol()
+            )
+        );
+        let pop = hour.pop.map(format_pop).unwrap_or_else(|| "-".to_string());
+        writeln!(
+            out,
+            "{}  {}  {}Rain {}",
+            hour.time,
+            temp.paint(
+                options
+                    .theme
+                    .scale(temp_color(units.temperature.to_celsius(hour.temp)))
+            ),
+            icon_column(&hour.weather, options),
+            format!("{:>4}", pop).paint(options.theme.reading)
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Bars used by [`sparkline`], lowest first.
+const SPARK_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Draws `values` as a row of bars scaled between their minimum and maximum.
+pub fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    values
+        .iter()
+        .map(|value| {
+            if range <= f64::EPSILON {
+                return SPARK_BARS[SPARK_BARS.len() / 2];
+            }
+            let step = ((value - min) / range * (SPARK_BARS.len() - 1) as f64).round();
+            SPARK_BARS[step as usize]
+        })
+        .collect()
+}
+
+/// Writes the recorded readings for one location as a sparkline of all of
+/// them followed by a table of the `limit` most recent, in local time.
+pub fn write_history(
+    out: &mut dyn Write,
+    location: &str,
+    readings: &[trend::Reading],
+    limit: usize,
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> io::Result<()> {
+    let out = &mut options.writer(out);
+    let temps: Vec<f64> = readings
+        .iter()
+        .map(|reading| convert_temp(reading.temp, units.temperature))
+        .collect();
+
+    writeln!(out, "\n{}", "History".bold().underline())?;
+    writeln!(
+        out,
+        "{}Location: {}",
+        options.icon("🌍 "),
+        location.paint(options.theme.location)
+    )?;
+    writeln!(
+        out,
+        "{}{}",
+        options.icon("📈 "),
+        sparkline(&temps).paint(options.theme.reading)
+    )?;
+    for (reading, temp) in readings
+        .iter()
+        .zip(&temps)
+        .skip(readings.len().saturating_sub(limit))
+    {
+        let time = DateTime::from_timestamp(reading.observed_at, 0)
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format(&format!("%a %b %d {}", options.time_format.pattern()))
+                    .to_string()
+            })
+            .unwrap_or_else(|| "N/A".to_string());
+        writeln!(
+            out,
+            "{}  {}{}",
+            time,
+            colored_temp(*temp, units, options.theme),
+            units.temperature.symbol()
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+pub fn write_forecast(
+    out: &mut dyn Write,
+    forecast: &ForecastResponse,
+    days: &[ForecastDay],
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> io::Result<()> {
+    let out = &mut options.writer(out);
+    let temp_unit = units.temperature.symbol();
+
+    writeln!(out, "\n{}", "5-Day Forecast".bold().underline())?;
+    writeln!(
+        out,
+        "{}Location: {}",
+        options.icon("🌍 "),
+        forecast.city.name.paint(options.theme.location)
+    )?;
+    for day in days {
+        let pop = day
+            .pop
+            .map(|pop| {
+                format!(
+                    "  Rain {}",
+                    format!("{:>4}", format_pop(pop)).paint(options.theme.reading)
+                )
+            })
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "{}{}: {}{} / {}{}{}",
+            icon_column(&day.weather, options),
+            day.date.format("%a %b %d"),
+            colored_temp(day.high, units, options.theme),
+            temp_unit,
+            colored_temp(day.low, units, options.theme),
+            temp_unit,
+            pop
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Width of the label column in `--compare` output.
+const COMPARE_LABEL_WIDTH: usize = 14;
+
+/// Widest each location's column in `--compare` output gets.
+const COMPARE_COLUMN_WIDTH: usize = 24;
+
+/// Narrowest a `--compare` column shrinks to on small terminals.
+const COMPARE_MIN_COLUMN_WIDTH: usize = 12;
+
+/// Column width that fits two locations side by side in `width` columns.
+fn comparison_column_width(width: usize) -> usize {
+    (width.saturating_sub(COMPARE_LABEL_WIDTH) / 2)
+        .clamp(COMPARE_MIN_COLUMN_WIDTH, COMPARE_COLUMN_WIDTH)
+}
+
+/// Pads or truncates a `--compare` cell to `width`, always leaving a space
+/// before the next column.
+fn comparison_cell(text: &str, width: usize) -> String {
+    format!("{:<width$}", truncate(text, width - 1), width = width)
+}
+
+/// Writes one `--compare` row, bolding whichever side `larger` points at.
+/// Cells are padded before they're styled so the escape codes don't throw
+/// off the alignment.
+fn write_comparison_row(
+    out: &mut dyn Write,
+    column: usize,
+    label: &str,
+    left: &str,
+    right: &str,
+    larger: Option<Ordering>,
+) -> io::Result<()> {
+    let left = comparison_cell(left, column);
+    let right = comparison_cell(right, column);
+    let (left, right) = match larger {
+        Some(Ordering::Greater) => (left.bold(), right.normal()),
+        Some(Ordering::Less) => (left.normal(), right.bold()),
+        _ => (left.normal(), right.normal()),
+    };
+    writeln!(
+        out,
+        "{:<width$}{}{}",
+        label,
+        left,
+        right,
+        width = COMPARE_LABEL_WIDTH
+    )?;
+    Ok(())
+}
+
+/// One-line verdict on which location is warmer and which is more humid.
+pub fn comparison_summary(
+    left: &WeatherReport,
+    right: &WeatherReport,
+    units: DisplayUnits,
+) -> String {
+    let delta =
+        convert_temp(left.temp, units.temperature) - convert_temp(right.temp, units.temperature);
+    let (warmer, colder) = if delta >= 0.0 {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    let temperature = if units.rounds_to_zero(delta) {
+        format!(
+            "{} and {} are the same temperature",
+            left.location, right.location
+        )
+    } else {
+        format!(
+            "{} is {}{} warmer than {}",
+            warmer.location,
+            format_temp(delta.abs(), units.precision),
+            units.temperature.symbol(),
+            colder.location
+        )
+    };
+
+    let humidity = match left.humidity.cmp(&right.humidity) {
+        Ordering::Equal => "equally humid".to_string(),
+        Ordering::Greater => format!("{} is more humid", left.location),
+        Ordering::Less => format!("{} is more humid", right.location),
+    };
+
+    format!("{}; {}", temperature, humidity)
+}
+
+/// Writes the key stats of two locations in aligned columns, narrowing them
+/// to fit the width and wrapping the closing summary.
+pub fn write_comparison(
+    out: &mut dyn Write,
+    left: &WeatherReport,
+    right: &WeatherReport,
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> io::Result<()> {
+    let out = &mut options.writer(out);
+    let column = comparison_column_width(options.width);
+    let temp_unit = units.temperature.symbol();
+    let temp = |kelvin: f64| {
+        format!(
+            "{}{}",
+            format_temp(convert_temp(kelvin, units.temperature), units.precision),
+            temp_unit
+        )
+    };
+    let description = |report: &WeatherReport| {
+        report
+            .weather
+            .as_ref()
+            .map(|w| w.description.clone())
+            .unwrap_or_default()
+    };
+    let wind = |report: &WeatherReport| {
+        format!(
+            "{} {} {}",
+            units.number(units.wind.convert(report.wind_speed)),
+            units.wind.symbol(),
+            report.wind_deg.map(get_wind_direction).unwrap_or("-")
+        )
+    };
+
+    writeln!(out, "\n{}", "Comparison".bold().underline())?;
+    writeln!(
+        out,
+        "{:<label$}{}{}",
+        "",
+        comparison_cell(&left.location, column).paint(options.theme.location),
+        comparison_cell(&right.location, column).paint(options.theme.location),
+        label = COMPARE_LABEL_WIDTH
+    )?;
+    write_comparison_row(
+        out,
+        column,
+        "Weather",
+        &description(left),
+        &description(right),
+        None,
+    )?;
+    write_comparison_row(
+        out,
+        column,
+        "Temperature",
+        &temp(left.temp),
+        &temp(right.temp),
+        left.temp.partial_cmp(&right.temp),
+    )?;
+    write_comparison_row(
+        out,
+        column,
+        "Feels like",
+        &temp(left.feels_like),
+        &temp(right.feels_like),
+        left.feels_like.partial_cmp(&right.feels_like),
+    )?;
+    write_comparison_row(
+        out,
+        column,
+        "High/Low",
+        &format!("{}/{}", temp(left.temp_max), temp(left.temp_min)),
+        &format!("{}/{}", temp(right.temp_max), temp(right.temp_min)),
+        None,
+    )?;
+    write_comparison_row(
+        out,
+        column,
+        "Humidity",
+        &format!("{}%", left.humidity),
+        &format!("{}%", right.humidity),
+        Some(left.humidity.cmp(&right.humidity)),
+    )?;
+    write_comparison_row(
+        out,
+        column,
+        "Pressure",
+        &format!("{} hPa", left.pressure),
+        &format!("{} hPa", right.pressure),
+        None,
+    )?;
+    write_comparison_row(out, column, "Wind", &wind(left), &wind(right), None)?;
+    writeln!(out)?;
+    for line in wrap(&comparison_summary(left, right, units), options.width) {
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-9;
+
+    fn sample_report() -> WeatherReport {
+        serde_json::from_str::<WeatherResponse>(
+            r#"{
+                "weather": [{"main": "Clouds", "description": "broken clouds"}],
+                "main": {
+                    "temp": 285.45,
+                    "feels_like": 284.2,
+                    "temp_min": 283.15,
+                    "temp_max": 287.04,
+                    "pressure": 1012,
+                    "humidity": 76
+                },
+                "visibility": 10000,
+                "clouds": {"all": 75},
+                "wind": {"speed": 4.1, "deg": 250, "gust": 7.2},
+                "sys": {"sunrise": 1700000000, "sunset": 1700030000},
+                "coord": {"lon": -0.1257, "lat": 51.5085},
+                "name": "London"
+            }"#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < EPSILON,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn ascii_art_is_plain_and_uniform() {
+        for main in [
+            "Clear",
+            "Clouds",
+            "Rain",
+            "Snow",
+            "Thunderstorm",
+            "Drizzle",
+            "Fog",
+            "",
+        ] {
+            let art = get_weather_ascii(main);
+            assert!(art.is_ascii(), "{}", main);
+            assert_eq!(art.lines().count(), 5, "{}", main);
+            assert!(art.lines().all(|line| line.len() <= 14), "{}", main);
+        }
+        assert_ne!(get_weather_ascii("clear"), get_weather_ascii("Clouds"));
+
+        let mut buffer = Vec::new();
+        let options = RenderOptions {
+            ascii: true,
+            ..RenderOptions::default()
+        };
+        write_report(
+            &mut buffer,
+            &sample_report(),
+            DisplayUnits::default(),
+            options,
+        )
+        .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains(get_weather_ascii("Clouds")));
+        assert!(!rendered.contains("☁️  Weather"));
+    }
+
+    #[test]
+    fn sparkline_scales_between_min_and_max() {
+        assert_eq!(sparkline(&[10.0, 12.0, 14.0, 17.0]), "▁▃▅█");
+        assert_eq!(sparkline(&[5.0, 5.0]), "▅▅");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn truncate_marks_the_cut() {
+        assert_eq!(truncate("light rain", 20), "light rain");
+        assert_eq!(truncate("light rain", 10), "light rain");
+        assert_eq!(
+            truncate("thunderstorm with heavy drizzle", 12),
+            "thunderstor…"
+        );
+        assert_eq!(truncate("ünïcödé", 4), "ünï…");
+    }
+
+    #[test]
+    fn visible_width_skips_color_codes() {
+        assert_eq!(visible_width("12°C"), 4);
+        assert_eq!(visible_width("\x1b[1;94m12°C\x1b[0m"), 4);
+        assert_eq!(visible_width("\x1b[2m"), 0);
+        assert_eq!(truncate("\x1b[94mParis\x1b[0m", 5), "\x1b[94mParis\x1b[0m");
+    }
+
+    #[test]
+    fn wrap_breaks_at_words() {
+        assert_eq!(
+            wrap("Tokyo is 5.0°C warmer than London", 16),
+            ["Tokyo is 5.0°C", "warmer than", "London"]
+        );
+        assert_eq!(wrap("short", 80), ["short"]);
+        assert_eq!(
+            wrap("Llanfairpwllgwyngyll is", 8),
+            ["Llanfairpwllgwyngyll", "is"]
+        );
+    }
+
+    #[test]
+    fn comparison_fits_narrow_terminals() {
+        assert_eq!(comparison_column_width(DEFAULT_WIDTH), COMPARE_COLUMN_WIDTH);
+        assert_eq!(comparison_column_width(50), 18);
+        assert_eq!(comparison_column_width(20), COMPARE_MIN_COLUMN_WIDTH);
+
+        let london = sample_report();
+        let mut buffer = Vec::new();
+        let options = RenderOptions {
+            width: 40,
+            ..RenderOptions::default()
+        };
+        write_comparison(
+            &mut buffer,
+            &london,
+            &london,
+            DisplayUnits::default(),
+            options,
+        )
+        .unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert!(output.lines().all(|line| visible_width(line) <= 40));
+    }
+
+    #[test]
+    fn comparison_summary_names_warmer_and_more_humid() {
+        let london = sample_report();
+        let mut tokyo = sample_report();
+        tokyo.location = "Tokyo".to_string();
+        tokyo.temp += 5.0;
+        tokyo.humidity = 60;
+
+        assert_eq!(
+            comparison_summary(&london, &tokyo, DisplayUnits::default()),
+            "Tokyo is 5.0°C warmer than London; London is more humid"
+        );
+        assert_eq!(
+            comparison_summary(&london, &london, DisplayUnits::default()),
+            "London and London are the same temperature; equally humid"
+        );
+    }
+
+    #[test]
+    fn summary_converts_gust_like_wind_speed() {
+        let summary = WeatherSummary::new(&sample_report(), DisplayUnits::default());
+        assert_close(summary.wind_gust.unwrap(), meters_per_second_to_kmh(7.2));
+
+        let mut report = sample_report();
+        report.wind_gust = None;
+        let summary = WeatherSummary::new(&report, DisplayUnits::default());
+        assert!(summary.wind_gust.is_none());
+    }
+
+    #[test]
+    fn json_output_is_versioned() {
+        let summary = WeatherSummary::new(&sample_report(), DisplayUnits::default());
+        let json = serde_json::to_value(JsonEnvelope::from(summary)).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["weather"]["location"], "London");
+        assert_eq!(json["weather"]["temperature_unit"], "C");
+    }
+
+    #[test]
+    fn wind_line_drops_unknown_direction_and_says_calm() {
+        let options = RenderOptions {
+            color: false,
+            ..RenderOptions::default()
+        };
+        let wind_line = |report: &WeatherReport| {
+            let mut buffer = Vec::new();
+            write_report(&mut buffer, report, DisplayUnits::default(), options).unwrap();
+            let rendered = String::from_utf8(buffer).unwrap();
+            rendered
+                .lines()
+                .find(|line| line.contains("Wind:"))
+                .unwrap()
+                .to_string()
+        };
+
+        let mut report = sample_report();
+        assert!(wind_line(&report).contains("from WSW →"));
+
+        report.wind_deg = None;
+        report.wind_gust = None;
+        assert_eq!(
+            wind_line(&report),
+            "🌪️  Wind: 14.8 km/h (Force 3 – Gentle breeze)"
+        );
+
+        report.wind_speed = 0.2;
+        report.wind_deg = Some(90.0);
+        assert_eq!(wind_line(&report), "🌪️  Wind: calm");
+    }
+
+    #[test]
+    fn beaufort_scale_band_boundaries() {
+        assert_eq!(beaufort_scale(0.0), (0, "Calm"));
+        assert_eq!(beaufort_scale(0.49), (0, "Calm"));
+        assert_eq!(beaufort_scale(0.5), (1, "Light air"));
+        assert_eq!(beaufort_scale(1.6), (2, "Light breeze"));
+        assert_eq!(beaufort_scale(3.4), (3, "Gentle breeze"));
+        assert_eq!(beaufort_scale(5.5), (4, "Moderate breeze"));
+        assert_eq!(beaufort_scale(7.99), (4, "Moderate breeze"));
+        assert_eq!(beaufort_scale(8.0), (5, "Fresh breeze"));
+        assert_eq!(beaufort_scale(10.8), (6, "Strong breeze"));
+        assert_eq!(beaufort_scale(13.9), (7, "Near gale"));
+        assert_eq!(beaufort_scale(17.2), (8, "Gale"));
+        assert_eq!(beaufort_scale(20.8), (9, "Strong gale"));
+        assert_eq!(beaufort_scale(24.5), (10, "Storm"));
+        assert_eq!(beaufort_scale(28.5), (11, "Violent storm"));
+        assert_eq!(beaufort_scale(32.69), (11, "Violent storm"));
+        assert_eq!(beaufort_scale(32.7), (12, "Hurricane force"));
+        assert_eq!(beaufort_scale(60.0), (12, "Hurricane force"));
+    }
+
+    #[test]
+    fn precipitation_reads_renamed_keys() {
+        let rain: Precipitation = serde_json::from_str(r#"{"1h": 2.5, "3h": 6.0}"#).unwrap();
+        assert_eq!(rain.latest(), Some((2.5, "1h")));
+
+        let snow: Precipitation = serde_json::from_str(r#"{"3h": 1.2}"#).unwrap();
+        assert_eq!(snow.latest(), Some((1.2, "3h")));
+        assert_eq!(serde_json::to_string(&snow).unwrap(), r#"{"3h":1.2}"#);
+
+        // Absent from the sample, so neither shows up
+        let report = sample_report();
+        assert!(report.rain.is_none() && report.snow.is_none());
+    }
+
+    #[test]
+    fn precipitation_block_lists_every_period_or_says_none() {
+        let options = RenderOptions {
+            no_emoji: true,
+            color: false,
+            ..RenderOptions::default()
+        };
+        let render = |report: &WeatherReport| {
+            let mut buffer = Vec::new();
+            write_precipitation(&mut buffer, report, DisplayUnits::default(), options).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(render(&sample_report()), "No precipitation\n");
+
+        let mut report = sample_report();
+        report.rain = Some(Precipitation {
+            one_hour: Some(0.4),
+            three_hours: Some(1.2),
+        });
+        report.snow = Some(Precipitation {
+            one_hour: None,
+            three_hours: Some(2.0),
+        });
+        assert_eq!(
+            render(&report),
+            "Rain: 0.4 mm (last 1h), 1.2 mm (last 3h)\nSnow: 2.0 mm (last 3h)\n"
+        );
+    }
+
+    #[test]
+    fn write_report_renders_into_any_writer() {
+        let mut buffer = Vec::new();
+        write_report(
+            &mut buffer,
+            &sample_report(),
+            DisplayUnits::default(),
+            RenderOptions::default(),
+        )
+        .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("Current Weather"));
+        assert!(rendered.contains("London"));
+        assert!(rendered.ends_with("\n\n"));
+        assert!(!rendered.contains("Cached reading"));
+
+        let mut stale = sample_report();
+        stale.stale = true;
+        stale.observed_at = Utc::now().timestamp() - 47 * 60;
+        let mut buffer = Vec::new();
+        write_report(
+            &mut buffer,
+            &stale,
+            DisplayUnits::default(),
+            RenderOptions::default(),
+        )
+        .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("Cached reading from 47 minutes ago"));
+    }
+
+    #[test]
+    fn suntimes_show_only_the_sun() {
+        let report = sample_report();
+        let mut buffer = Vec::new();
+        write_suntimes(&mut buffer, &report, RenderOptions::default()).unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+        assert!(rendered.contains("Sunrise: "));
+        assert!(rendered.contains("Sunset: "));
+        assert!(rendered.contains("Day length: "));
+        assert!(!rendered.contains("Temperature"));
+
+        let times = SunTimes::new(&report);
+        assert_eq!(
+            times.sunrise_local,
+            format_timestamp(report.sunrise, report.timezone, TimeFormat::H24)
+        );
+        assert_eq!(
+            times.day_length_minutes,
+            (report.sunset - report.sunrise) / 60
+        );
+    }
+
+    #[test]
+    fn color_themes_pick_palettes_for_the_background() {
+        assert_eq!(Theme::from(ColorTheme::Dark), Theme::default());
+        assert_eq!(Theme::DARK.scale(Color::BrightRed), Some(Color::BrightRed));
+        assert_eq!(Theme::LIGHT.scale(Color::BrightRed), Some(Color::Red));
+        assert_eq!(Theme::LIGHT.scale(Color::Yellow), Some(Color::Yellow));
+        assert_eq!(Theme::MONO.scale(Color::BrightRed), None);
+        assert_eq!("Paris".paint(Theme::MONO.location), "Paris".normal());
+        assert_eq!(
+            "Paris".paint(Theme::LIGHT.location),
+            "Paris".color(Color::Blue)
+        );
+    }
+
+    #[test]
+    fn plain_writer_strips_codes_split_across_writes() {
+        let mut buffer = Vec::new();
+        let mut out = PlainWriter::new(&mut buffer, true);
+        out.write_all(b"\x1b[1;9").unwrap();
+        out.write_all("4m12°C\x1b[0m\n".as_bytes()).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "12°C\n");
+
+        let mut buffer = Vec::new();
+        PlainWriter::new(&mut buffer, false)
+            .write_all(b"\x1b[2mx")
+            .unwrap();
+        assert_eq!(buffer, b"\x1b[2mx");
+    }
+
+    #[test]
+    fn no_emoji_starts_every_line_with_its_label() {
+        let options = RenderOptions {
+            no_emoji: true,
+            color: false,
+            ..RenderOptions::default()
+        };
+        let mut report = sample_report();
+        report.stale = true;
+        report.anomaly = Some(1.0);
+        let mut buffer = Vec::new();
+        write_report(&mut buffer, &report, DisplayUnits::default(), options).unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+
+        assert!(rendered.contains("\nLocation: London\n"));
+        assert!(rendered.contains("\nWeather: broken clouds\n"));
+        for line in rendered.lines().filter(|line| !line.is_empty()) {
+            assert!(
+                // Wind arrows are text, not emoji, so they stay
+                line.chars()
+                    .all(|c| c.is_ascii() || "°–—µ³↓↙←↖↑↗→↘".contains(c)),
+                "{}",
+                line
+            );
+            assert!(
+                line.starts_with(|c: char| c.is_ascii_alphanumeric()),
+                "{}",
+                line
+            );
+            assert!(!line.ends_with(' '), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn moon_phase_matches_known_dates() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(moon_phase(date(2024, 1, 11)), ("New Moon", "🌑"));
+        assert_eq!(moon_phase(date(2024, 1, 25)), ("Full Moon", "🌕"));
+        assert_eq!(moon_phase(date(2023, 8, 31)), ("Full Moon", "🌕"));
+        assert_eq!(moon_phase(date(2025, 3, 29)), ("New Moon", "🌑"));
+        // A week after the January 2024 new moon
+        assert_eq!(moon_phase(date(2024, 1, 18)), ("First Quarter", "🌓"));
+    }
+
+    #[test]
+    fn format_trend_shows_direction_in_display_units() {
+        let units = |unit| DisplayUnits::new(Units::Metric, Some(unit));
+        assert_eq!(format_trend(1.2, units(TempUnit::Celsius)), "↑ +1.2°");
+        assert_eq!(format_trend(-0.8, units(TempUnit::Kelvin)), "↓ -0.8°");
+        assert_eq!(format_trend(1.0, units(TempUnit::Fahrenheit)), "↑ +1.8°");
+        assert_eq!(format_trend(0.01, units(TempUnit::Celsius)), "→ 0.0°");
+        let whole = units(TempUnit::Celsius).with_precision(0);
+        assert_eq!(format_trend(0.4, whole), "→ 0°");
+        assert_eq!(format_trend(1.6, whole), "↑ +2°");
+    }
+
+    #[test]
+    fn csv_rows_quote_fields_with_commas() {
+        let mut report = sample_report();
+        report.location = "London, England, GB".to_string();
+        let row = format_csv_row(&report, DisplayUnits::default());
+        assert!(row.starts_with("\"London, England, GB\",12.3,"), "{}", row);
+        assert_eq!(
+            row.split(',').count(),
+            CSV_HEADER.split(',').count() + 2,
+            "{}",
+            row
+        );
+
+        assert_eq!(csv_field("Paris"), "Paris");
+        assert_eq!(csv_field(r#"say "hi""#), r#""say ""hi""""#);
+    }
+
+    #[test]
+    fn format_summary_reads_as_a_sentence() {
+        let mut report = sample_report();
+        report.temp = 285.15;
+        report.feels_like = 282.15;
+        report.wind_speed = 15.0 / 3.6;
+        report.wind_deg = Some(315.0);
+        report.weather = Some(Weather {
+            main: "Clouds".to_string(),
+            description: "broken clouds".to_string(),
+        });
+        let whole = DisplayUnits::default().with_precision(0);
+        assert_eq!(
+            format_summary(&report, whole),
+            "It's currently 12°C and cloudy in London, feeling like 9°C with a 15 km/h northwest wind."
+        );
+
+        // Missing pieces are dropped, not invented
+        report.weather = None;
+        report.feels_like = report.temp;
+        report.wind_deg = None;
+        assert_eq!(
+            format_summary(&report, whole),
+            "It's currently 12°C in London, with a 15 km/h wind."
+        );
+
+        report.wind_speed = 0.1;
+        assert_eq!(
+            format_summary(
+                &report,
+                DisplayUnits::new(Units::Imperial, None).with_precision(0)
+            ),
+            "It's currently 54°F in London, with calm winds."
+        );
+        assert_eq!(compass_name("NNW"), "north-northwest");
+        assert_eq!(compass_name("E"), "east");
+    }
+
+    #[test]
+    fn format_template_substitutes_placeholders() {
+        let report = sample_report();
+        assert_eq!(
+            format_template(
+                "{city}: {temp} {emoji} {humidity}",
+                &report,
+                DisplayUnits::default(),
+                RenderOptions::default()
+            ),
+            "London: 12.3°C ☁️ 76%"
+        );
+        assert_eq!(
+            format_template(
+                "{wind_speed} {wind_dir}",
+                &report,
+                DisplayUnits::default(),
+                RenderOptions::default()
+            ),
+            "14.8 km/h WSW"
+        );
+        assert_eq!(
+            format_template(
+                "{pressure}",
+                &report,
+                DisplayUnits::new(Units::Imperial, None),
+                RenderOptions::default()
+            ),
+            "29.88 inHg"
+        );
+        // Unknown placeholders and stray braces are left alone
+        assert_eq!(
+            format_template(
+                "{nope} {city",
+                &report,
+                DisplayUnits::default(),
+                RenderOptions::default()
+            ),
+            "{nope} {city"
+        );
+    }
+
+    #[test]
+    fn next_hours_uses_local_time_and_keeps_pop() {
+        let forecast: ForecastResponse = serde_json::from_str(
+            r#"{
+                "list": [
+                    {"dt": 1700000000, "main": {"temp": 283.15, "temp_max": 284.0, "temp_min": 282.0,
+                     "feels_like": 282.0, "humidity": 80, "pressure": 1010},
+                     "weather": [{"main": "Rain", "description": "light rain"}], "pop": 0.6},
+                    {"dt": 1700010800, "main": {"temp": 284.15, "temp_max": 285.0, "temp_min": 283.0,
+                     "feels_like": 283.0, "humidity": 75, "pressure": 1011},
+                     "weather": [{"main": "Clouds", "description": "overcast clouds"}]}
+                ],
+                "city": {"name": "London", "timezone": 3600}
+            }"#,
+        )
+        .unwrap();
+
+        let hours = next_hours(&forecast, 8, TempUnit::Celsius);
+        assert_eq!(hours.len(), 2);
+        // 1700000000 is 22:13 UTC
+        assert_eq!(hours[0].time, "23:13");
+        assert_close(hours[0].temp, 10.0);
+        assert_eq!(hours[0].pop, Some(0.6));
+        assert_eq!(hours[1].pop, None);
+        assert_eq!(format_pop(0.6), "60%");
+        assert_eq!(format_pop(0.0), "0%");
+    }
+
+    #[test]
+    fn forecast_day_keeps_the_highest_pop() {
+        // Noon and 13:00 UTC, so both land on the same date in London
+        let forecast: ForecastResponse = serde_json::from_str(
+            r#"{
+                "list": [
+                    {"dt": 1700049600, "main": {"temp": 283.15, "temp_max": 284.0, "temp_min": 282.0,
+                     "feels_like": 282.0, "humidity": 80, "pressure": 1010},
+                     "weather": [{"main": "Rain", "description": "light rain"}], "pop": 0.2},
+                    {"dt": 1700053200, "main": {"temp": 284.15, "temp_max": 285.0, "temp_min": 283.0,
+                     "feels_like": 283.0, "humidity": 75, "pressure": 1011},
+                     "weather": [{"main": "Rain", "description": "light rain"}], "pop": 0.6}
+                ],
+                "city": {"name": "London"}
+            }"#,
+        )
+        .unwrap();
+
+        let days = summarize_forecast(&forecast, 5, TempUnit::Celsius);
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].pop, Some(0.6));
+
+        let mut forecast = forecast;
+        forecast.list.iter_mut().for_each(|entry| entry.pop = None);
+        assert_eq!(
+            summarize_forecast(&forecast, 5, TempUnit::Celsius)[0].pop,
+            None
+        );
+    }
+
+    #[test]
+    fn forecast_days_split_at_the_locations_midnight() {
+        let forecast = |timezone: i32| -> ForecastResponse {
+            // 14:00 and 16:00 UTC on 15 November, 23:00 and 01:00 in Tokyo
+            serde_json::from_str(&format!(
+                r#"{{
+                    "list": [
+                        {{"dt": 1700056800, "main": {{"temp": 283.15, "temp_max": 284.0, "temp_min": 282.0,
+                         "feels_like": 282.0, "humidity": 80, "pressure": 1010}}, "weather": []}},
+                        {{"dt": 1700064000, "main": {{"temp": 284.15, "temp_max": 285.0, "temp_min": 283.0,
+                         "feels_like": 283.0, "humidity": 75, "pressure": 1011}}, "weather": []}}
+                    ],
+                    "city": {{"name": "Tokyo", "timezone": {}}}
+                }}"#,
+                timezone
+            ))
+            .unwrap()
+        };
+
+        let days = summarize_forecast(&forecast(0), 5, TempUnit::Celsius);
+        assert_eq!(days.len(), 1);
+
+        let days = summarize_forecast(&forecast(9 * 3600), 5, TempUnit::Celsius);
+        let dates: Vec<NaiveDate> = days.iter().map(|day| day.date).collect();
+        assert_eq!(
+            dates,
+            [
+                NaiveDate::from_ymd_opt(2023, 11, 15).unwrap(),
+                NaiveDate::from_ymd_opt(2023, 11, 16).unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn kelvin_to_celsius_known_values() {
+        assert_close(kelvin_to_celsius(273.15), 0.0);
+        assert_close(kelvin_to_celsius(373.15), 100.0);
+        assert_close(kelvin_to_celsius(0.0), -273.15);
+    }
+
+    #[test]
+    fn kelvin_to_fahrenheit_known_values() {
+        assert_close(kelvin_to_fahrenheit(273.15), 32.0);
+        assert_close(kelvin_to_fahrenheit(373.15), 212.0);
+        assert_close(kelvin_to_fahrenheit(233.15), -40.0);
+    }
+
+    #[test]
+    fn meters_per_second_to_kmh_known_values() {
+        assert_close(meters_per_second_to_kmh(0.0), 0.0);
+        assert_close(meters_per_second_to_kmh(10.0), 36.0);
+    }
+
+    #[test]
+    fn wind_direction_cardinal_points() {
+        assert_eq!(get_wind_direction(0.0), "N");
+        assert_eq!(get_wind_direction(90.0), "E");
+        assert_eq!(get_wind_direction(180.0), "S");
+        assert_eq!(get_wind_direction(270.0), "W");
+    }
+
+    #[test]
+    fn wind_direction_handles_out_of_range_degrees() {
+        assert_eq!(get_wind_direction(360.0), "N");
+        assert_eq!(get_wind_direction(359.9), "N");
+        assert_eq!(get_wind_direction(-45.0), "NW");
+        assert_eq!(get_wind_direction(720.0), "N");
+        assert_eq!(get_wind_direction(348.75), "N");
+    }
+
+    #[test]
+    fn wind_arrow_points_downwind() {
+        assert_eq!(wind_arrow(0.0), '↓');
+        assert_eq!(wind_arrow(90.0), '←');
+        assert_eq!(wind_arrow(180.0), '↑');
+        assert_eq!(wind_arrow(270.0), '→');
+        assert_eq!(wind_arrow(315.0), '↘');
+        assert_eq!(wind_arrow(22.4), '↓');
+        assert_eq!(wind_arrow(22.5), '↙');
+        assert_eq!(wind_arrow(359.9), '↓');
+        assert_eq!(wind_arrow(-45.0), '↘');
+    }
+
+    #[test]
+    fn format_timestamp_falls_back_on_invalid_input() {
+        assert_eq!(format_timestamp(i64::MAX, 0, TimeFormat::H24), INVALID_TIME);
+        assert_eq!(format_timestamp(0, 100_000, TimeFormat::H24), INVALID_TIME);
+    }
+
+    #[test]
+    fn format_timestamp_applies_utc_offset() {
+        // 2023-11-14 22:13:20 UTC
+        assert_eq!(format_timestamp(1_700_000_000, 0, TimeFormat::H24), "22:13");
+        assert_eq!(
+            format_timestamp(1_700_000_000, 9 * 3600, TimeFormat::H24),
+            "07:13"
+        );
+        assert_eq!(
+            format_timestamp(1_700_000_000, -5 * 3600, TimeFormat::H24),
+            "17:13"
+        );
+    }
+
+    #[test]
+    fn format_timestamp_in_twelve_hour_time() {
+        assert_eq!(
+            format_timestamp(1_700_000_000, 0, TimeFormat::H12),
+            "10:13 PM"
+        );
+        assert_eq!(
+            format_timestamp(1_700_000_000, 9 * 3600, TimeFormat::H12),
+            "07:13 AM"
+        );
+    }
+
+    #[test]
+    fn time_format_follows_the_locale_region() {
+        assert_eq!(TimeFormat::for_locale("en_US.UTF-8"), TimeFormat::H12);
+        assert_eq!(TimeFormat::for_locale("en_AU"), TimeFormat::H12);
+        assert_eq!(TimeFormat::for_locale("en_GB.UTF-8"), TimeFormat::H24);
+        assert_eq!(TimeFormat::for_locale("de_DE@euro"), TimeFormat::H24);
+        assert_eq!(TimeFormat::for_locale("C"), TimeFormat::H24);
+    }
+
+    #[test]
+    fn meters_per_second_to_mph_known_values() {
+        assert_close(meters_per_second_to_mph(0.0), 0.0);
+        assert!((meters_per_second_to_mph(10.0) - 22.369).abs() < 1e-3);
+    }
+
+    #[test]
+    fn meters_per_second_to_knots_known_values() {
+        assert_close(meters_per_second_to_knots(0.0), 0.0);
+        assert!((meters_per_second_to_knots(10.0) - 19.438).abs() < 1e-3);
+        // One knot is one nautical mile (1852 m) per hour
+        assert_close(meters_per_second_to_knots(1852.0 / 3600.0), 1.0);
+    }
+
+    #[test]
+    fn hpa_to_inhg_known_values() {
+        assert!((hpa_to_inhg(1013.25) - 29.921).abs() < 1e-3);
+    }
+
+    #[test]
+    fn meters_to_miles_known_values() {
+        assert_close(meters_to_miles(1609.344), 1.0);
+        assert!((meters_to_miles(10000.0) - 6.214).abs() < 1e-3);
+    }
+
+    #[test]
+    fn dew_point_known_values() {
+        assert!((dew_point(20.0, 50.0) - 9.26).abs() < 0.01);
+        assert!((dew_point(30.0, 70.0) - 23.93).abs() < 0.01);
+        assert!((dew_point(-5.0, 80.0) - -7.9).abs() < 0.05);
+        // Saturated air condenses at the air temperature
+        assert!((dew_point(25.0, 100.0) - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn temperature_alert_uses_the_display_unit() {
+        let report = sample_report();
+        let metric = DisplayUnits::default();
+        assert_eq!(
+            temperature_alert(&report, metric, Some(10.0), None, RenderOptions::default())
+                .as_deref(),
+            Some("🚨 Alert: London is 12.3°C, above the 10.0°C limit")
+        );
+        assert_eq!(
+            temperature_alert(
+                &report,
+                metric,
+                Some(15.0),
+                Some(5.0),
+                RenderOptions::default()
+            ),
+            None
+        );
+        assert_eq!(
+            temperature_alert(&report, metric, None, None, RenderOptions::default()),
+            None
+        );
+
+        let imperial = DisplayUnits::new(Units::Imperial, None);
+        assert_eq!(
+            temperature_alert(
+                &report,
+                imperial,
+                None,
+                Some(60.0),
+                RenderOptions::default()
+            )
+            .as_deref(),
+            Some("🚨 Alert: London is 54.1°F, below the 60.0°F limit")
+        );
+    }
+
+    #[test]
+    fn extreme_warning_thresholds_are_in_celsius() {
+        let celsius = |c: f64| c + 273.15;
+        assert_eq!(extreme_warning(celsius(20.0)), None);
+        assert_eq!(extreme_warning(celsius(39.3)), None);
+        assert!(extreme_warning(celsius(39.5))
+            .unwrap()
+            .contains("Extreme heat"));
+        assert_eq!(extreme_warning(celsius(-26.9)), None);
+        assert!(extreme_warning(celsius(-27.1))
+            .unwrap()
+            .contains("Extreme cold"));
+        // 110°F, whatever unit it ends up displayed in
+        assert!(extreme_warning(fahrenheit_to_kelvin(110.0)).is_some());
+
+        let mut report = sample_report();
+        report.feels_like = celsius(45.0);
+        let mut buffer = Vec::new();
+        write_report(
+            &mut buffer,
+            &report,
+            DisplayUnits::default(),
+            RenderOptions::default(),
+        )
+        .unwrap();
+        assert!(String::from_utf8(buffer)
+            .unwrap()
+            .contains("Extreme heat — stay hydrated"));
+    }
+
+    #[test]
+    fn parse_city_trims_and_rejects_blank_names() {
+        assert_eq!(
+            Location::parse_city("  São Paulo ").unwrap(),
+            Location::City("São Paulo".to_string())
+        );
+        assert!(Location::parse_city("").is_err());
+        assert!(Location::parse_city("   ").is_err());
+    }
+
+    #[test]
+    fn location_query_pairs() {
+        assert_eq!(
+            Location::City("São Paulo".to_string()).query(),
+            vec![("q", "São Paulo".to_string())]
+        );
+        assert_eq!(
+            Location::Coordinates {
+                lat: 47.6,
+                lon: -122.3
+            }
+            .query(),
+            vec![("lat", "47.6".to_string()), ("lon", "-122.3".to_string())]
+        );
+        assert_eq!(
+            Location::CityId(2643743).query(),
+            vec![("id", "2643743".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_zip_defaults_country_to_us() {
+        assert_eq!(
+            Location::parse_zip("98101").unwrap(),
+            Location::Zip {
+                code: "98101".to_string(),
+                country: "us".to_string()
+            }
+        );
+        assert_eq!(
+            Location::parse_zip("SW1A 1AA, GB").unwrap(),
+            Location::Zip {
+                code: "SW1A 1AA".to_string(),
+                country: "gb".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_country_code_wants_two_letters() {
+        assert_eq!(parse_country_code("ca").unwrap(), "CA");
+        assert_eq!(parse_country_code(" GB ").unwrap(), "GB");
+        for bad in ["", "C", "CAN", "C1", "ü"] {
+            assert!(
+                matches!(parse_country_code(bad), Err(Error::Config(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn parse_zip_rejects_malformed_input() {
+        assert!(Location::parse_zip("").is_err());
+        assert!(Location::parse_zip("981&01").is_err());
+        assert!(Location::parse_zip("98101,usa").is_err());
+        assert!(Location::parse_zip("98101,").is_err());
+    }
+
+    #[test]
+    fn fahrenheit_to_kelvin_round_trips() {
+        assert_close(fahrenheit_to_kelvin(32.0), 273.15);
+        assert_close(kelvin_to_fahrenheit(fahrenheit_to_kelvin(98.6)), 98.6);
+    }
+
+    #[test]
+    fn heat_index_matches_nws_table() {
+        // Values from the NWS heat index chart
+        assert_eq!(heat_index(90.0, 60.0).round(), 100.0);
+        assert_eq!(heat_index(96.0, 50.0).round(), 108.0);
+        assert_eq!(heat_index(100.0, 40.0).round(), 109.0);
+        assert_eq!(heat_index(86.0, 90.0).round(), 105.0);
+    }
+
+    #[test]
+    fn wind_chill_matches_nws_table() {
+        // Values from the NWS wind chill chart
+        assert_eq!(wind_chill(40.0, 5.0).round(), 36.0);
+        assert_eq!(wind_chill(30.0, 10.0).round(), 21.0);
+        assert_eq!(wind_chill(0.0, 15.0).round(), -19.0);
+        assert_eq!(wind_chill(-10.0, 30.0).round(), -39.0);
+    }
+
+    #[test]
+    fn apparent_temperature_only_in_extremes() {
+        assert!(apparent_temperature(70.0, 50.0, 10.0).is_none());
+        assert!(apparent_temperature(40.0, 50.0, 2.0).is_none());
+        assert!(apparent_temperature(90.0, 60.0, 0.0).is_some());
+        assert!(apparent_temperature(30.0, 50.0, 10.0).is_some());
+    }
+
+    #[test]
+    fn apparent_temperature_skipped_when_close_to_air_temperature() {
+        assert!(apparent_temperature(80.0, 40.0, 0.0).is_none());
+        assert!(apparent_temperature(50.0, 50.0, 3.1).is_none());
+        assert!(apparent_temperature(50.0, 50.0, 15.0).is_some());
+    }
+
+    #[test]
+    fn daylight_measures_from_sunrise_and_to_sunset() {
+        let sunrise = 1_700_000_000;
+        let sunset = sunrise + 10 * 3600;
+        assert_eq!(
+            daylight(
+                sunrise + 3 * 3600 + 5 * 60,
+                sunrise,
+                sunset,
+                RenderOptions::default()
+            ),
+            "☀️  Daytime — 3h 05m since sunrise, 6h 55m until sunset"
+        );
+        assert_eq!(
+            daylight(sunrise - 60, sunrise, sunset, RenderOptions::default()),
+            "🌙 Nighttime"
+        );
+        assert_eq!(
+            daylight(sunset, sunrise, sunset, RenderOptions::default()),
+            "🌙 Nighttime"
+        );
+    }
+
+    #[test]
+    fn format_pressure_trend_says_which_way() {
+        let units = DisplayUnits::default();
+        assert_eq!(format_pressure_trend(-3, units), "falling ↓ 3 hPa");
+        assert_eq!(format_pressure_trend(2, units), "rising ↑ 2 hPa");
+        assert_eq!(format_pressure_trend(0, units), "steady →");
+        assert_eq!(
+            format_pressure_trend(-3, DisplayUnits::new(Units::Imperial, None)),
+            "falling ↓ 0.09 inHg"
+        );
+    }
+
+    #[test]
+    fn format_feels_like_delta_says_warmer_or_colder() {
+        let units = DisplayUnits::default();
+        assert_eq!(
+            format_feels_like_delta(-4.0, units).as_deref(),
+            Some("-4.0° colder")
+        );
+        assert_eq!(
+            format_feels_like_delta(1.25, units).as_deref(),
+            Some("+1.2° warmer")
+        );
+        assert_eq!(format_feels_like_delta(-0.04, units), None);
+        assert_eq!(
+            format_feels_like_delta(-2.0, DisplayUnits::new(Units::Imperial, None)).as_deref(),
+            Some("-3.6° colder")
+        );
+    }
+
+    #[test]
+    fn format_anomaly_says_which_way() {
+        let units = DisplayUnits::default();
+        assert_eq!(format_anomaly(3.2, units), "3.2° above seasonal average");
+        assert_eq!(format_anomaly(-1.04, units), "1.0° below seasonal average");
+        assert_eq!(format_anomaly(0.01, units), "right at the seasonal average");
+        // A difference is scaled, not offset, into Fahrenheit
+        assert_eq!(
+            format_anomaly(5.0, DisplayUnits::new(Units::Imperial, None)),
+            "9.0° above seasonal average"
+        );
+    }
+
+    #[test]
+    fn format_age_picks_the_largest_unit() {
+        assert_eq!(format_age(30), "less than a minute ago");
+        assert_eq!(format_age(60), "1 minute ago");
+        assert_eq!(format_age(47 * 60 + 59), "47 minutes ago");
+        assert_eq!(format_age(2 * 3600), "2 hours ago");
+        assert_eq!(format_age(86400), "1 day ago");
+        assert_eq!(format_age(-5), "less than a minute ago");
+    }
+
+    #[test]
+    fn format_day_length_known_values() {
+        assert_eq!(format_day_length(0, 10 * 3600 + 42 * 60), "10h 42m");
+        assert_eq!(format_day_length(1_000, 1_000 + 5 * 60 + 59), "0h 05m");
+        assert_eq!(format_day_length(0, 24 * 3600), "24h 00m");
+    }
+
+    #[test]
+    fn format_day_length_clamps_negative_to_zero() {
+        assert_eq!(format_day_length(5_000, 1_000), "0h 00m");
+    }
+
+    #[test]
+    fn format_quiet_is_a_single_line() {
+        let report = sample_report();
+        assert_eq!(
+            format_quiet(&report, DisplayUnits::default(), RenderOptions::default()),
+            "London: 12.3°C ☁️"
+        );
+        assert_eq!(
+            format_quiet(
+                &report,
+                DisplayUnits::new(Units::Imperial, None),
+                RenderOptions::default()
+            ),
+            "London: 54.1°F ☁️"
+        );
+    }
+
+    #[test]
+    fn notification_names_the_city_and_conditions() {
+        let (title, body) = format_notification(&sample_report(), DisplayUnits::default());
+        assert_eq!(title, "Weather in London");
+        assert_eq!(body, "12.3°C, broken clouds");
+    }
+
+    #[test]
+    fn emoji_style_picks_the_icon_set() {
+        assert_eq!(get_weather_emoji("Rain", EmojiStyle::Emoji), "🌧️");
+        assert_eq!(get_weather_emoji("Rain", EmojiStyle::NerdFont), "\u{e318}");
+        assert_eq!(get_weather_emoji("Rain", EmojiStyle::None), "");
+
+        let none = RenderOptions {
+            emoji: EmojiStyle::None,
+            ..RenderOptions::default()
+        };
+        let report = sample_report();
+        assert_eq!(
+            format_quiet(&report, DisplayUnits::default(), none),
+            "London: 12.3°C"
+        );
+        assert_eq!(
+            format_template("{emoji}", &report, DisplayUnits::default(), none),
+            ""
+        );
+    }
+
+    #[test]
+    fn aqi_label_covers_every_band() {
+        assert_eq!(aqi_label(1), "Good");
+        assert_eq!(aqi_label(2), "Fair");
+        assert_eq!(aqi_label(3), "Moderate");
+        assert_eq!(aqi_label(4), "Poor");
+        assert_eq!(aqi_label(5), "Very Poor");
+        assert_eq!(aqi_label(0), "Unknown");
+    }
+
+    #[test]
+    fn uv_category_bands() {
+        assert_eq!(uv_category(0.0), "Low");
+        assert_eq!(uv_category(2.9), "Low");
+        assert_eq!(uv_category(3.0), "Moderate");
+        assert_eq!(uv_category(7.0), "High");
+        assert_eq!(uv_category(8.0), "Very High");
+        assert_eq!(uv_category(11.0), "Extreme");
+    }
+
+    #[test]
+    fn uv_color_bands() {
+        assert_eq!(uv_color(0.0), Color::BrightGreen);
+        assert_eq!(uv_color(2.9), Color::BrightGreen);
+        assert_eq!(uv_color(3.0), Color::BrightYellow);
+        assert_eq!(uv_color(5.9), Color::BrightYellow);
+        assert_eq!(uv_color(6.0), ORANGE);
+        assert_eq!(uv_color(7.9), ORANGE);
+        assert_eq!(uv_color(8.0), Color::BrightRed);
+        assert_eq!(uv_color(10.9), Color::BrightRed);
+        assert_eq!(uv_color(11.0), Color::BrightMagenta);
+        assert_eq!(uv_color(14.0), Color::BrightMagenta);
+    }
+
+    #[test]
+    fn convert_temp_respects_unit() {
+        assert_close(convert_temp(273.15, TempUnit::Celsius), 0.0);
+        assert_close(convert_temp(273.15, TempUnit::Fahrenheit), 32.0);
+        assert_close(convert_temp(273.15, TempUnit::Kelvin), 273.15);
+    }
+
+    #[test]
+    fn humidity_color_bands() {
+        assert_eq!(humidity_color(0), Color::Yellow);
+        assert_eq!(humidity_color(29), Color::Yellow);
+        assert_eq!(humidity_color(30), Color::BrightGreen);
+        assert_eq!(humidity_color(50), Color::BrightGreen);
+        assert_eq!(humidity_color(55), Color::BrightCyan);
+        assert_eq!(humidity_color(61), Color::BrightMagenta);
+        assert_eq!(humidity_color(100), Color::BrightMagenta);
+    }
+
+    #[test]
+    fn temp_color_bands_are_in_celsius() {
+        assert_eq!(temp_color(-5.0), Color::BrightBlue);
+        assert_eq!(temp_color(0.0), Color::BrightBlue);
+        assert_eq!(temp_color(15.0), Color::BrightGreen);
+        assert_eq!(temp_color(35.0), Color::BrightRed);
+        // 95°F is hot whichever unit it's shown in
+        assert_eq!(
+            temp_color(TempUnit::Fahrenheit.to_celsius(95.0)),
+            Color::BrightRed
+        );
+    }
+
+    #[test]
+    fn format_temp_handles_sign() {
+        assert_eq!(format_temp(3.456, 1), "3.5");
+        assert_eq!(format_temp(0.0, 1), "0.0");
+        assert_eq!(format_temp(-5.26, 1), "-5.3");
+        assert_eq!(format_temp(kelvin_to_celsius(263.15), 1), "-10.0");
+        // Kelvin is shown as-is and never picks up a sign
+        assert_eq!(
+            format_temp(convert_temp(263.0, TempUnit::Kelvin), 1),
+            "263.0"
+        );
+        assert_eq!(format_temp(3.456, 0), "3");
+        assert_eq!(format_temp(3.456, 2), "3.46");
+        assert_eq!(format_temp(-5.2649, 3), "-5.265");
+    }
+
+    #[test]
+    fn format_temp_never_prints_negative_zero() {
+        assert_eq!(format_temp(-0.04, 1), "0.0");
+        assert_eq!(format_temp(-0.4, 0), "0");
+        assert_eq!(format_temp(-0.0, 1), "0.0");
+        assert_eq!(format_temp(-0.05001, 1), "-0.1");
+    }
+}
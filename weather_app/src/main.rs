@@ -1,208 +1,1208 @@
-use chrono::{DateTime, Local};
-use clap::Parser;
+use chrono::{Local, TimeDelta, Utc};
+use clap::{Args, Parser, Subcommand};
 use colored::Colorize;
-use serde::Deserialize;
+use futures::future::join_all;
+use indicatif::ProgressBar;
+use notify_rust::Notification;
 use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use tracing::level_filters::LevelFilter;
+use tracing::warn;
+use tracing_subscriber::EnvFilter;
+use weather_app::client::{
+    parse_base_url, API_BASE_URL, DEFAULT_LANG, DEFAULT_RETRIES, DEFAULT_TIMEOUT,
+};
+use weather_app::config::{resolve_api_key, DEFAULT_CITY_ENV};
+use weather_app::trend;
+use weather_app::units::{DEFAULT_PRECISION, MAX_PRECISION};
+use weather_app::{
+    did_you_mean, format_csv_row, format_notification, format_quiet, format_summary,
+    format_template, next_hours, parse_country_code, summarize_forecast, temperature_alert,
+    terminal_width, write_comparison, write_forecast, write_history, write_hourly, write_report,
+    write_separator, write_suntimes, Api, Cache, ColorTheme, Config, DisplayUnits, EmojiStyle,
+    Error, JsonEnvelope, Location, OpenMeteoClient, Paint, PlainWriter, Provider, RenderOptions,
+    SunTimes, TempUnit, TimeFormat, Units, WeatherClient, WeatherProvider, WeatherReport,
+    WeatherSummary, WindUnit, CSV_HEADER,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// The city to get the weather for
-    city: String,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Display temperature in Fahrenheit instead of Celsius
-    #[arg(short, long)]
-    fahrenheit: bool,
-}
+    // Used when no subcommand is given, so `weather_app London` shows the
+    // current weather
+    #[command(flatten)]
+    current: CurrentArgs,
 
-#[derive(Deserialize, Debug)]
-struct WeatherResponse {
-    main: Main,
-    weather: Vec<Weather>,
-    name: String,
-    wind: Wind,
-    sys: Sys,
+    #[command(flatten)]
+    global: GlobalArgs,
 }
 
-#[derive(Deserialize, Debug)]
-struct Main {
-    temp: f64,
-    temp_max: f64,
-    temp_min: f64,
-    feels_like: f64,
-    humidity: i32,
+#[derive(Subcommand)]
+enum Command {
+    /// Show the current weather (the default when no subcommand is given)
+    Current(CurrentArgs),
+
+    /// Show the forecast for the next 5 days, or the next 24 hours
+    Forecast(ForecastArgs),
+
+    /// Show just the sunrise, sunset and day length
+    Suntimes(SuntimesArgs),
+
+    /// Show the temperatures recorded for a city on earlier runs
+    History {
+        /// The city (or favorite alias) to show; defaults to the saved location
+        city: Option<String>,
+
+        /// How many of the most recent readings to list
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Fetch the saved location and every favorite into the cache, e.g. from
+    /// cron, printing one status line each
+    Refresh,
+
+    /// Manage the saved default location and favorites
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
 }
 
-#[derive(Deserialize, Debug)]
-struct Weather {
-    description: String,
-    main: String,
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Save CITY as the default location (or as a favorite with --alias)
+    Save {
+        city: String,
+
+        /// Name to save the city under, so it can be recalled by passing NAME as the city
+        #[arg(long, value_name = "NAME")]
+        alias: Option<String>,
+    },
+
+    /// List the saved default location and favorites
+    List,
 }
 
-#[derive(Deserialize, Debug)]
-struct Wind {
-    speed: f64,
-    deg: Option<f64>,
+// Options that apply to every subcommand
+#[derive(Args)]
+struct GlobalArgs {
+    /// OpenWeatherMap API key (overrides WEATHER_API_KEY and the config file)
+    #[arg(long, value_name = "KEY", global = true)]
+    api_key: Option<String>,
+
+    /// OpenWeatherMap API root to use instead of the real one, e.g. an
+    /// internal proxy (overrides base_url in the config file)
+    #[arg(long, value_name = "URL", value_parser = parse_base_url, global = true)]
+    base_url: Option<String>,
+
+    /// ISO 3166 country code used to narrow down city names, e.g. "US"
+    #[arg(long, value_name = "CODE", value_parser = parse_country_code, global = true)]
+    country: Option<String>,
+
+    /// Language for weather descriptions, e.g. "es", "fr", "de" (OpenWeatherMap only)
+    #[arg(long, value_name = "CODE", default_value = DEFAULT_LANG, global = true)]
+    lang: String,
+
+    /// Where to get the weather from
+    #[arg(long, value_enum, default_value_t = Provider::OpenWeatherMap, global = true)]
+    provider: Provider,
+
+    /// OpenWeatherMap endpoints to use: the free 2.5 ones, or one-call for One
+    /// Call API 3.0 (needs a subscription; falls back to 2.5 without one)
+    #[arg(long, value_enum, default_value_t = Api::Classic, global = true)]
+    api: Api,
+
+    /// Unit system for temperatures and wind speed
+    #[arg(short, long, value_enum, default_value_t = Units::Metric, global = true)]
+    units: Units,
+
+    /// Unit for wind speeds, regardless of --units
+    #[arg(long, value_enum, global = true)]
+    wind_unit: Option<WindUnit>,
+
+    /// Display temperature in Fahrenheit regardless of --units
+    #[arg(short, long, global = true)]
+    fahrenheit: bool,
+
+    /// Display temperature in Kelvin, unconverted, regardless of --units
+    #[arg(short, long, conflicts_with = "fahrenheit", global = true)]
+    kelvin: bool,
+
+    /// Display temperature in Celsius regardless of --units
+    #[arg(short, long, conflicts_with_all = ["fahrenheit", "kelvin"], global = true)]
+    celsius: bool,
+
+    /// Decimal places for temperatures, wind speeds and other measurements
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_PRECISION as u8,
+        value_parser = clap::value_parser!(u8).range(..=MAX_PRECISION as i64),
+        global = true
+    )]
+    precision: u8,
+
+    /// Also append the report, without colors, to this file under a timestamp
+    #[arg(long, value_name = "PATH", global = true)]
+    output: Option<PathBuf>,
+
+    /// Always fetch fresh data instead of using the on-disk cache
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Show the last cached reading instead of contacting the API. Cached
+    /// readings are also used automatically when the API can't be reached.
+    #[arg(long, conflicts_with = "no_cache", global = true)]
+    offline: bool,
+
+    /// Where to keep cached responses and readings (overrides
+    /// WEATHER_CACHE_DIR and the user's cache directory)
+    #[arg(long, value_name = "DIR", global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// How long cached responses stay valid, in minutes
+    #[arg(long, value_name = "MINUTES", default_value_t = 10, global = true)]
+    cache_ttl: u64,
+
+    /// Seconds to wait for the API before giving up
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_TIMEOUT.as_secs(), global = true)]
+    timeout: u64,
+
+    /// How many times to retry after a connection error or server error
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_RETRIES, global = true)]
+    retries: u32,
+
+    /// Check the arguments and API key, then print the URL that would be
+    /// requested (API key redacted) instead of contacting the API
+    #[arg(long, conflicts_with = "offline", global = true)]
+    dry_run: bool,
+
+    /// Print each request URL (API key redacted) and raw response to stderr
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Log diagnostics at this level or above to stderr: error, warn, info, debug
+    /// or trace (overrides RUST_LOG)
+    #[arg(long, value_name = "LEVEL", global = true)]
+    log_level: Option<LevelFilter>,
+
+    /// Icons for the weather condition: color emoji, Nerd Font glyphs or none
+    #[arg(long, value_enum, value_name = "STYLE", default_value_t = EmojiStyle::Emoji, global = true)]
+    emoji_style: EmojiStyle,
+
+    /// Leave out all emoji, including the ones before each label, for screen
+    /// readers and plain terminals
+    #[arg(long, conflicts_with = "emoji_style", global = true)]
+    no_emoji: bool,
+
+    /// Colors suited to the terminal background: dark, light or mono
+    #[arg(long, value_enum, value_name = "THEME", default_value_t = ColorTheme::Dark, global = true)]
+    color_theme: ColorTheme,
+
+    /// Clock for sunrise and sunset times: 12 or 24 (defaults to the one
+    /// the locale uses)
+    #[arg(long, value_enum, value_name = "12|24", global = true)]
+    time_format: Option<TimeFormat>,
+
+    /// Disable colored output (also honors the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    no_color: bool,
 }
 
-#[derive(Deserialize, Debug)]
-struct Sys {
-    sunrise: i64,
-    sunset: i64,
+#[derive(Args)]
+struct LocationArgs {
+    /// The city (or cities, or favorite aliases) to get the weather for; defaults to the saved location
+    #[arg(conflicts_with_all = ["lat", "lon", "zip", "city_id"])]
+    city: Vec<String>,
+
+    /// Also read cities from this file, one per line ("-" for stdin); blank
+    /// lines and lines starting with # are skipped
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["lat", "lon", "zip", "city_id"])]
+    file: Option<PathBuf>,
+
+    /// Latitude to get the weather for (use together with --lon)
+    #[arg(long, requires = "lon", allow_negative_numbers = true)]
+    lat: Option<f64>,
+
+    /// Longitude to get the weather for (use together with --lat)
+    #[arg(long, requires = "lat", allow_negative_numbers = true)]
+    lon: Option<f64>,
+
+    /// ZIP/postal code to get the weather for, optionally with a country (defaults to "us")
+    #[arg(long, value_name = "CODE[,COUNTRY]", conflicts_with_all = ["lat", "lon"])]
+    zip: Option<String>,
+
+    /// OpenWeatherMap city ID to get the weather for, which unlike a name is
+    /// never ambiguous
+    #[arg(long, value_name = "ID", conflicts_with_all = ["lat", "lon", "zip"])]
+    city_id: Option<u64>,
 }
 
-fn kelvin_to_celsius(kelvin: f64) -> f64 {
-    kelvin - 273.15
+#[derive(Args)]
+struct CurrentArgs {
+    #[command(flatten)]
+    location: LocationArgs,
+
+    /// Print a JSON summary instead of the human-readable report
+    #[arg(short, long)]
+    json: bool,
+
+    /// Print only a one-line summary like "London: 12.3°C ☁️"
+    #[arg(short, long, conflicts_with = "json")]
+    quiet: bool,
+
+    /// Print one plain-English sentence per location, e.g. "It's currently
+    /// 12°C and cloudy in London, ..."
+    #[arg(long, conflicts_with_all = ["json", "quiet", "csv", "format", "compare"])]
+    summary: bool,
+
+    /// Print a CSV header and one row per location, for spreadsheets
+    #[arg(long, conflicts_with_all = ["json", "quiet", "compare"])]
+    csv: bool,
+
+    /// Print each report using a template, e.g. "{city}: {temp} {emoji}".
+    /// Placeholders: {city} {temp} {feels_like} {high} {low} {humidity} {pressure}
+    /// {wind_speed} {wind_dir} {description} {emoji} {sunrise} {sunset}
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["json", "quiet", "csv", "compare"])]
+    format: Option<String>,
+
+    /// Draw the conditions as ASCII art instead of an emoji, for terminals
+    /// and fonts that don't render emoji well
+    #[arg(long)]
+    ascii: bool,
+
+    /// Also show the air quality index and particulate levels
+    #[arg(long)]
+    air_quality: bool,
+
+    /// Also show the UV index (needs a One Call subscription on OpenWeatherMap)
+    #[arg(long)]
+    uv: bool,
+
+    /// Also compare the temperature with the average for the date (needs a
+    /// Statistical Weather API subscription on OpenWeatherMap)
+    #[arg(long)]
+    anomaly: bool,
+
+    /// Print the provider's response body exactly as received, including
+    /// fields this tool doesn't use
+    #[arg(long, conflicts_with_all = ["json", "quiet", "csv", "summary", "format", "compare", "ascii", "air_quality", "uv", "anomaly", "offline", "alert_above", "alert_below", "notify"])]
+    raw: bool,
+
+    /// Compare two locations side by side, e.g. `--compare London Tokyo`
+    #[arg(long, conflicts_with_all = ["json", "quiet"])]
+    compare: bool,
+
+    /// Print an alert and exit with status 6 when the temperature is above
+    /// TEMP, in the display unit
+    #[arg(long, value_name = "TEMP", allow_negative_numbers = true)]
+    alert_above: Option<f64>,
+
+    /// Print an alert and exit with status 6 when the temperature is below
+    /// TEMP, in the display unit
+    #[arg(long, value_name = "TEMP", allow_negative_numbers = true)]
+    alert_below: Option<f64>,
+
+    /// Also show the city, temperature and conditions as a desktop notification
+    #[arg(long)]
+    notify: bool,
+
+    /// Keep running and refresh the report every SECONDS (Ctrl-C to stop)
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    watch: Option<u64>,
 }
 
-fn kelvin_to_fahrenheit(kelvin: f64) -> f64 {
-    (kelvin - 273.15) * 9.0 / 5.0 + 32.0
+#[derive(Args)]
+struct ForecastArgs {
+    #[command(flatten)]
+    location: LocationArgs,
+
+    /// Print the forecast as JSON
+    #[arg(short, long)]
+    json: bool,
+
+    /// Show the next 24 hours in 3-hour steps instead of the next 5 days
+    #[arg(long)]
+    hourly: bool,
+
+    /// Keep running and refresh the forecast every SECONDS (Ctrl-C to stop)
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    watch: Option<u64>,
 }
 
-fn meters_per_second_to_kmh(mps: f64) -> f64 {
-    mps * 3.6
+#[derive(Args)]
+struct SuntimesArgs {
+    #[command(flatten)]
+    location: LocationArgs,
+
+    /// Print the sun times as JSON
+    #[arg(short, long)]
+    json: bool,
 }
 
-fn get_wind_direction(degrees: f64) -> &'static str {
-    let directions = [
-        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
-        "NW", "NNW",
-    ];
-    let index = ((degrees + 11.25) % 360.0 / 22.5) as usize;
-    directions[index]
+/// What to fetch and how to print it, from whichever subcommand was used.
+enum Mode<'a> {
+    Current(&'a CurrentArgs),
+    Forecast(&'a ForecastArgs),
+    Suntimes(&'a SuntimesArgs),
 }
 
-fn get_weather_emoji(weather_main: &str) -> &str {
-    match weather_main.to_lowercase().as_str() {
-        "clear" => "☀️",
-        "clouds" => "☁️",
-        "rain" => "🌧️",
-        "snow" => "❄️",
-        "thunderstorm" => "⛈️",
-        "drizzle" => "🌦️",
-        "mist" | "fog" => "🌫️",
-        _ => "🌡️",
+impl Mode<'_> {
+    fn location(&self) -> &LocationArgs {
+        match self {
+            Mode::Current(args) => &args.location,
+            Mode::Forecast(args) => &args.location,
+            Mode::Suntimes(args) => &args.location,
+        }
+    }
+
+    fn json(&self) -> bool {
+        match self {
+            Mode::Current(args) => args.json,
+            Mode::Forecast(args) => args.json,
+            Mode::Suntimes(args) => args.json,
+        }
     }
-}
 
-fn format_timestamp(timestamp: i64) -> String {
-    let datetime = DateTime::from_timestamp(timestamp, 0)
-        .expect("Invalid timestamp")
-        .with_timezone(&Local);
-    datetime.format("%H:%M").to_string()
+    fn watch(&self) -> Option<u64> {
+        match self {
+            Mode::Current(args) => args.watch,
+            Mode::Forecast(args) => args.watch,
+            Mode::Suntimes(_) => None,
+        }
+    }
+
+    /// Whether the output is meant for a person rather than a script
+    fn decorated(&self) -> bool {
+        match self {
+            Mode::Current(args) => {
+                !args.json
+                    && !args.quiet
+                    && !args.csv
+                    && !args.summary
+                    && !args.raw
+                    && args.format.is_none()
+            }
+            Mode::Forecast(args) => !args.json,
+            Mode::Suntimes(args) => !args.json,
+        }
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Read the API key from the environment
-    let api_key = env::var("WEATHER_API_KEY")
-        .map_err(|_| "Please set the WEATHER_API_KEY environment variable")?;
+async fn main() -> ExitCode {
+    match try_main().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            e.into()
+        }
+    }
+}
 
-    let args = Cli::parse();
+async fn try_main() -> weather_app::Result<()> {
+    let Cli {
+        command,
+        current,
+        global,
+    } = Cli::parse();
 
-    let url = format!(
-        "https://api.openweathermap.org/data/2.5/weather?q={}&appid={}",
-        args.city, api_key
-    );
+    init_logging(global.log_level);
 
-    let response = reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Failed to fetch weather data: {}", e))?
-        .json::<WeatherResponse>()
-        .await
-        .map_err(|e| format!("Failed to parse weather data: {}", e))?;
+    // Colors are turned off per render through `RenderOptions::color`
+    let no_color = no_color(&global);
 
-    let temp = if args.fahrenheit {
-        kelvin_to_fahrenheit(response.main.temp)
+    let mut config = Config::load()?;
+
+    let temperature_override = if global.celsius {
+        Some(TempUnit::Celsius)
+    } else if global.fahrenheit {
+        Some(TempUnit::Fahrenheit)
+    } else if global.kelvin {
+        Some(TempUnit::Kelvin)
     } else {
-        kelvin_to_celsius(response.main.temp)
+        None
     };
+    let units = DisplayUnits::new(global.units, temperature_override)
+        .with_wind_unit(global.wind_unit)
+        .with_precision(global.precision.into());
 
-    let temp_max = if args.fahrenheit {
-        kelvin_to_fahrenheit(response.main.temp_max)
-    } else {
-        kelvin_to_celsius(response.main.temp_max)
+    let mode = match &command {
+        None => Mode::Current(&current),
+        Some(Command::Current(args)) => Mode::Current(args),
+        Some(Command::Forecast(args)) => Mode::Forecast(args),
+        Some(Command::Suntimes(args)) => Mode::Suntimes(args),
+        Some(Command::History { city, limit }) => {
+            let options = RenderOptions {
+                no_emoji: global.no_emoji,
+                theme: global.color_theme.into(),
+                time_format: time_format(&global),
+                color: !no_color,
+                ..RenderOptions::default()
+            };
+            let cache = Cache::open(global.cache_dir.clone(), Duration::ZERO)?;
+            return print_history(cache, &config, city.as_deref(), *limit, units, options);
+        }
+        Some(Command::Refresh) => return refresh(&global, &config).await,
+        Some(Command::Config { action }) => return configure(&mut config, action),
     };
 
-    let temp_min = if args.fahrenheit {
-        kelvin_to_fahrenheit(response.main.temp_min)
+    let show_progress =
+        mode.decorated() && !global.verbose && !no_color && io::stdout().is_terminal();
+
+    let locations = resolve_locations(mode.location(), &config)?;
+    if let Mode::Current(args) = mode {
+        if args.compare && locations.len() != 2 {
+            return Err(Error::Config(
+                "--compare needs exactly two locations".to_string(),
+            ));
+        }
+    }
+
+    let timeout = Duration::from_secs(global.timeout);
+
+    match global.provider {
+        Provider::OpenWeatherMap => {
+            let client = openweathermap_client(&global, &config)?;
+            if global.dry_run {
+                return dry_run(&mode, &client, &locations);
+            }
+            run(&global, &mode, &client, &locations, units, show_progress).await
+        }
+        Provider::OpenMeteo => {
+            let client = OpenMeteoClient::new(timeout)?
+                .with_country(global.country.clone())
+                .with_verbose(global.verbose);
+            if global.dry_run {
+                return dry_run(&mode, &client, &locations);
+            }
+            run(&global, &mode, &client, &locations, units, show_progress).await
+        }
+    }
+}
+
+/// The OpenWeatherMap client set up from the command line and config file.
+fn openweathermap_client(
+    global: &GlobalArgs,
+    config: &Config,
+) -> weather_app::Result<WeatherClient> {
+    let api_key = resolve_api_key(global.api_key.clone(), config)?;
+    let cache = if global.no_cache {
+        None
     } else {
-        kelvin_to_celsius(response.main.temp_min)
+        Cache::open(
+            global.cache_dir.clone(),
+            Duration::from_secs(global.cache_ttl * 60),
+        )?
+    };
+    let base_url = match (&global.base_url, &config.base_url) {
+        (Some(flag), _) => flag.clone(),
+        (None, Some(configured)) => parse_base_url(configured)?,
+        (None, None) => API_BASE_URL.to_string(),
+    };
+    Ok(
+        WeatherClient::new(api_key, Duration::from_secs(global.timeout))?
+            .with_base_url(base_url)
+            .with_retries(global.retries)
+            .with_cache(cache)
+            .with_country(global.country.clone())
+            .with_lang(global.lang.clone())
+            .with_verbose(global.verbose)
+            .with_no_emoji(global.no_emoji)
+            .with_offline(global.offline)
+            .with_api(global.api),
+    )
+}
+
+/// Fetches the weather and forecast for the saved location and every
+/// favorite straight from the API, so the cache is warm for the next check.
+/// Prints one line per place rather than a report.
+async fn refresh(global: &GlobalArgs, config: &Config) -> weather_app::Result<()> {
+    if global.provider != Provider::OpenWeatherMap {
+        return Err(Error::Config(format!(
+            "Only OpenWeatherMap responses are cached, so there is nothing to refresh from {}",
+            global.provider
+        )));
+    }
+    if global.no_cache || global.offline {
+        return Err(Error::Config(
+            "refresh fills the cache, so it can't be combined with --no-cache or --offline"
+                .to_string(),
+        ));
+    }
+
+    let default_city = config.default_city();
+    let saved: Vec<(&str, &str)> = default_city
+        .iter()
+        .map(|city| ("default", city.as_str()))
+        .chain(
+            config
+                .favorites
+                .iter()
+                .map(|(alias, city)| (alias.as_str(), city.as_str())),
+        )
+        .collect();
+    if saved.is_empty() {
+        return Err(Error::Config(
+            "Nothing to refresh; save places with `config save <CITY> [--alias <NAME>]`"
+                .to_string(),
+        ));
+    }
+
+    let client = openweathermap_client(global, config)?.with_refresh(true);
+    let results = join_all(saved.iter().map(|(_, city)| {
+        let client = &client;
+        async move {
+            let location = Location::parse_city(city)?;
+            let (weather, forecast) = tokio::join!(
+                WeatherProvider::fetch(client, &location),
+                WeatherProvider::fetch_forecast(client, &location)
+            );
+            forecast?;
+            weather
+        }
+    }))
+    .await;
+
+    let mut failures = Vec::new();
+    for ((name, _), result) in saved.iter().zip(results) {
+        match result {
+            Ok(report) => println!("{}: refreshed {}", name, report.location),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                failures.push(e);
+            }
+        }
+    }
+    match summarize_failures(&failures, saved.len(), "refresh") {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// https://no-color.org: any non-empty `NO_COLOR` disables color, like
+/// `--no-color`.
+fn no_color(global: &GlobalArgs) -> bool {
+    global.no_color || env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+fn time_format(global: &GlobalArgs) -> TimeFormat {
+    global.time_format.unwrap_or_else(TimeFormat::from_locale)
+}
+
+/// Prints the request each location would start with, without sending it.
+fn dry_run<P: WeatherProvider>(
+    mode: &Mode<'_>,
+    provider: &P,
+    locations: &[Location],
+) -> weather_app::Result<()> {
+    let forecast = matches!(mode, Mode::Forecast(_));
+    for location in locations {
+        println!("GET {}", provider.request_url(location, forecast)?);
+    }
+    Ok(())
+}
+
+/// Sends diagnostics to stderr so stdout only ever carries the report.
+/// `--log-level` applies to this crate's own events; without it RUST_LOG
+/// is used as is, and with neither nothing is logged.
+fn init_logging(level: Option<LevelFilter>) {
+    let filter = match level {
+        Some(level) => EnvFilter::new(format!("weather_app={}", level)),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("off")),
     };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .init();
+}
 
-    let feels_like = if args.fahrenheit {
-        kelvin_to_fahrenheit(response.main.feels_like)
+/// Turns the location arguments into locations, falling back to the saved
+/// default when none were given.
+fn resolve_locations(args: &LocationArgs, config: &Config) -> weather_app::Result<Vec<Location>> {
+    let mut cities = args.city.clone();
+    if let Some(path) = &args.file {
+        cities.extend(read_cities(path)?);
+    }
+    if let Some(id) = args.city_id {
+        return Ok(vec![Location::CityId(id)]);
+    }
+    let locations = match (args.lat, args.lon, &args.zip) {
+        (Some(lat), Some(lon), None) => vec![Location::Coordinates { lat, lon }],
+        (None, None, Some(zip)) => vec![Location::parse_zip(zip)?],
+        (None, None, None) if !cities.is_empty() => cities
+            .iter()
+            .map(|city| Location::parse_city(&config.resolve_city(city)))
+            .collect::<weather_app::Result<_>>()?,
+        (None, None, None) => match config.default_city() {
+            Some(city) => vec![Location::parse_city(&config.resolve_city(&city))?],
+            None => {
+                return Err(Error::Config(
+                    "Please provide a city, --zip, --city-id, or both --lat and --lon \
+                     (or set WEATHER_DEFAULT_CITY or save a default with `config save <CITY>`)"
+                        .to_string(),
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::Config(
+                "Please provide a city, --zip, --city-id, or both --lat and --lon".to_string(),
+            ))
+        }
+    };
+    Ok(locations)
+}
+
+/// The lines of `path`, or of stdin when it is "-", in order and trimmed,
+/// skipping blank lines and `#` comments.
+fn read_cities(path: &Path) -> weather_app::Result<Vec<String>> {
+    let contents = if path == Path::new("-") {
+        io::read_to_string(io::stdin())
+            .map_err(|e| Error::Other(format!("Failed to read cities from stdin: {}", e)))?
     } else {
-        kelvin_to_celsius(response.main.feels_like)
+        fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("Failed to read {}: {}", path.display(), e)))?
     };
+    let cities: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if cities.is_empty() {
+        return Err(Error::Config(format!(
+            "No cities found in {}",
+            if path == Path::new("-") {
+                "stdin".to_string()
+            } else {
+                path.display().to_string()
+            }
+        )));
+    }
+    Ok(cities)
+}
 
-    let temp_unit = if args.fahrenheit { "°F" } else { "°C" };
-    let wind_speed_kmh = meters_per_second_to_kmh(response.wind.speed);
+/// Runs a `config` subcommand.
+fn configure(config: &mut Config, action: &ConfigCommand) -> weather_app::Result<()> {
+    match action {
+        ConfigCommand::Save { city, alias } => {
+            match alias {
+                Some(alias) => {
+                    config.favorites.insert(alias.clone(), city.clone());
+                }
+                None => config.default_location = Some(city.clone()),
+            }
+            let path = config.save()?;
+            match alias {
+                Some(alias) => println!("Saved {} as '{}' in {}", city, alias, path.display()),
+                None => println!(
+                    "Saved {} as the default location in {}",
+                    city,
+                    path.display()
+                ),
+            }
+        }
+        ConfigCommand::List => print_favorites(config),
+    }
+    Ok(())
+}
 
-    // Get wind direction if available
-    let wind_direction = response.wind.deg.map(get_wind_direction).unwrap_or("-");
+fn print_favorites(config: &Config) {
+    match config.default_city() {
+        Some(city) if config.default_location.as_ref() != Some(&city) => {
+            println!("Default: {} (from {})", city, DEFAULT_CITY_ENV)
+        }
+        Some(city) => println!("Default: {}", city),
+        None => println!("Default: (none, save one with `config save <CITY>`)"),
+    }
+    for (alias, city) in &config.favorites {
+        println!("{}: {}", alias, city);
+    }
+}
 
-    // Get the first weather description or provide a default
-    let weather = response
-        .weather
-        .first()
-        .map(|w| (w.description.clone(), w.main.clone()))
+/// Prints the readings recorded for `city` (or the saved location) by earlier
+/// runs.
+fn print_history(
+    cache: Option<Cache>,
+    config: &Config,
+    city: Option<&str>,
+    limit: usize,
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> weather_app::Result<()> {
+    let city = match city.map(str::to_string).or_else(|| config.default_city()) {
+        Some(city) => config.resolve_city(&city),
+        None => {
+            return Err(Error::Config(
+                "Please provide a city (or save a default with `config save <CITY>`)".to_string(),
+            ))
+        }
+    };
+    let found = cache
+        .map(|cache| trend::readings(&cache, &city))
         .unwrap_or_default();
+    if found.is_empty() {
+        return Err(Error::NotFound(format!(
+            "No readings recorded for '{}' yet",
+            city
+        )));
+    }
 
-    println!("\n{}", "Current Weather".bold().underline());
-    println!("🌍 Location: {}", response.name.bright_blue());
-    println!(
-        "{}  Weather: {}",
-        get_weather_emoji(&weather.1),
-        weather.0.bright_yellow()
-    );
-    println!(
-        "🌡️  Temperature: {}{:.1}{}",
-        if temp <0.0 {"-"} else { ""},
-        temp.abs().to_string().bright_green(),
-        temp_unit
-    );
+    let mut out = io::stdout().lock();
+    for (location, readings) in &found {
+        write_history(&mut out, location, readings, limit, units, options)?;
+    }
+    Ok(())
+}
 
-    println!(
-        "🤔 Feels like: {}{:.1}{}",
-        if feels_like < 0.0 { "-" } else { "" },  
-        feels_like.abs().to_string().bright_green(), 
-        temp_unit
-    );
+/// Reports once, or forever in `--watch` mode.
+async fn run<P: WeatherProvider>(
+    global: &GlobalArgs,
+    mode: &Mode<'_>,
+    provider: &P,
+    locations: &[Location],
+    units: DisplayUnits,
+    show_progress: bool,
+) -> weather_app::Result<()> {
+    if let Some(seconds) = mode.watch() {
+        let interval = Duration::from_secs(seconds);
+        loop {
+            if !mode.json() {
+                // Clear the screen and move the cursor home before redrawing
+                print!("\x1B[2J\x1B[H");
+            }
+            report(global, mode, provider, locations, units, show_progress).await?;
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+            }
+        }
+    }
 
-    println!(
-        "🌡️  Today's High/Low: {}{:.1}{}/{}{:.1}{}",
-        if temp_max < 0.0 { "-" } else { "" },  
-        temp_max.abs().to_string().bright_green(),
-        temp_unit,
-        if temp_min < 0.0 { "-" } else { "" },  
-        temp_min.abs().to_string().bright_green(),
-        temp_unit
-    );
+    let (alerts, failures): (Vec<Error>, Vec<Error>) =
+        report(global, mode, provider, locations, units, show_progress)
+            .await?
+            .into_iter()
+            .partition(|e| matches!(e, Error::Alert(_)));
+    if let Some(error) = summarize_failures(&failures, locations.len(), "get weather for") {
+        return Err(error);
+    }
+    if !alerts.is_empty() {
+        return Err(Error::Alert(format!(
+            "{} of {} location(s) crossed an alert limit",
+            alerts.len(),
+            locations.len()
+        )));
+    }
 
-    println!(
-        "💧 Humidity: {}%",
-        response.main.humidity.to_string().bright_cyan()
-    );
+    Ok(())
+}
 
-    // Wind information
-    println!(
-        "🌪️  Wind: {:.1} km/h from {}",
-        wind_speed_kmh.to_string().bright_magenta(),
-        wind_direction.bright_magenta()
+/// One error standing for every location that failed, e.g. "Failed to
+/// refresh 2 of 3 location(s)", or `None` if none did.
+fn summarize_failures(failures: &[Error], total: usize, action: &str) -> Option<Error> {
+    let first = failures.first()?;
+    let message = format!(
+        "Failed to {} {} of {} location(s)",
+        action,
+        failures.len(),
+        total
     );
+    // Keep the specific exit code when every location failed the same way
+    let alike = failures.iter().all(|e| e.exit_code() == first.exit_code());
+    Some(if alike {
+        first.with_message(message)
+    } else {
+        Error::Other(message)
+    })
+}
 
-    // Sun information
-    println!(
-        "🌅 Sunrise: {}",
-        format_timestamp(response.sys.sunrise).bright_yellow()
-    );
-    println!(
-        "🌇 Sunset: {}\n",
-        format_timestamp(response.sys.sunset).bright_yellow()
-    );
+/// Fetches and prints the weather for every location, returning the errors
+/// for the ones that failed and an [`Error::Alert`] for each one beyond an
+/// alert limit.
+async fn report<P: WeatherProvider>(
+    global: &GlobalArgs,
+    mode: &Mode<'_>,
+    provider: &P,
+    locations: &[Location],
+    units: DisplayUnits,
+    show_progress: bool,
+) -> weather_app::Result<Vec<Error>> {
+    let spinner = show_progress.then(|| {
+        let names: Vec<String> = locations.iter().map(|l| l.to_string()).collect();
+        let spinner = ProgressBar::new_spinner();
+        spinner.set_message(format!("Fetching weather for {}...", names.join(", ")));
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        spinner
+    });
+
+    let options = RenderOptions {
+        width: terminal_width(),
+        ascii: matches!(mode, Mode::Current(args) if args.ascii),
+        emoji: global.emoji_style,
+        no_emoji: global.no_emoji,
+        theme: global.color_theme.into(),
+        time_format: time_format(global),
+        color: !no_color(global),
+    };
+
+    // Fire all requests at once, but report the results in the order given
+    let mut failures = Vec::new();
+    match mode {
+        Mode::Forecast(args) => {
+            let results = join_all(locations.iter().map(|l| provider.fetch_forecast(l))).await;
+            if let Some(spinner) = &spinner {
+                spinner.finish_and_clear();
+            }
+            let forecasts = successes(global, provider, locations, results, &mut failures).await;
+            emit(global, |out| {
+                for (i, forecast) in forecasts.iter().enumerate() {
+                    if i > 0 && !args.json {
+                        write_separator(out, options)?;
+                    }
+                    if args.hourly {
+                        let hours = next_hours(forecast, 8, units.temperature);
+                        if args.json {
+                            writeln!(out, "{}", serde_json::to_string_pretty(&hours)?)?;
+                        } else {
+                            write_hourly(out, forecast, &hours, units, options)?;
+                        }
+                        continue;
+                    }
+                    let days = summarize_forecast(forecast, 5, units.temperature);
+                    if args.json {
+                        writeln!(out, "{}", serde_json::to_string_pretty(&days)?)?;
+                    } else {
+                        write_forecast(out, forecast, &days, units, options)?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Mode::Suntimes(args) => {
+            let results = join_all(locations.iter().map(|l| async move {
+                if global.offline {
+                    provider.fetch_cached(l).await
+                } else {
+                    provider.fetch(l).await
+                }
+            }))
+            .await;
+            if let Some(spinner) = &spinner {
+                spinner.finish_and_clear();
+            }
+            let reports = successes(global, provider, locations, results, &mut failures).await;
+            emit(global, |out| {
+                for (i, report) in reports.iter().enumerate() {
+                    if args.json {
+                        let times = SunTimes::new(report);
+                        writeln!(out, "{}", serde_json::to_string_pretty(&times)?)?;
+                        continue;
+                    }
+                    if i > 0 {
+                        write_separator(out, options)?;
+                    }
+                    write_suntimes(out, report, options)?;
+                }
+                Ok(())
+            })?;
+        }
+        Mode::Current(args) if args.raw => {
+            let results = join_all(locations.iter().map(|l| provider.fetch_raw(l))).await;
+            if let Some(spinner) = &spinner {
+                spinner.finish_and_clear();
+            }
+            let bodies = successes(global, provider, locations, results, &mut failures).await;
+            emit(global, |out| {
+                for body in &bodies {
+                    writeln!(out, "{}", body)?;
+                }
+                Ok(())
+            })?;
+        }
+        Mode::Current(args) => {
+            let results = join_all(
+                locations
+                    .iter()
+                    .map(|l| fetch_report(global, args, provider, l)),
+            )
+            .await;
+            if let Some(spinner) = &spinner {
+                spinner.finish_and_clear();
+            }
+            let reports = successes(global, provider, locations, results, &mut failures).await;
+            let alerts: Vec<String> = reports
+                .iter()
+                .filter_map(|r| {
+                    temperature_alert(r, units, args.alert_above, args.alert_below, options)
+                })
+                .collect();
+            emit(global, |out| {
+                if args.compare {
+                    if let [left, right] = reports.as_slice() {
+                        write_comparison(out, left, right, units, options)?;
+                    }
+                } else {
+                    write_reports(out, mode, args, &reports, units, options)?;
+                }
+                // Kept out of JSON and CSV so the output still parses
+                if !args.json && !args.csv {
+                    for alert in &alerts {
+                        writeln!(
+                            options.writer(out),
+                            "{}",
+                            alert.paint(options.theme.danger).bold()
+                        )?;
+                    }
+                }
+                Ok(())
+            })?;
+            if args.json || args.csv {
+                for alert in &alerts {
+                    eprintln!("{}", alert);
+                }
+            }
+            if args.notify {
+                for report in &reports {
+                    notify(report, units).await;
+                }
+            }
+            failures.extend(alerts.into_iter().map(Error::Alert));
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Shows a desktop notification summarizing `report`. Failing to show it,
+/// e.g. with no notification daemon running, is reported but not fatal.
+async fn notify(report: &WeatherReport, units: DisplayUnits) {
+    let (title, body) = format_notification(report, units);
+    let shown = tokio::task::spawn_blocking(move || {
+        Notification::new()
+            .appname("weather_app")
+            .summary(&title)
+            .body(&body)
+            .show()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()));
+    if let Err(e) = shown {
+        eprintln!("{}: Failed to show a notification: {}", report.location, e);
+    }
+}
 
-    
+/// Renders each current-weather report in the format `args` asks for.
+fn write_reports(
+    out: &mut dyn Write,
+    mode: &Mode<'_>,
+    args: &CurrentArgs,
+    reports: &[WeatherReport],
+    units: DisplayUnits,
+    options: RenderOptions,
+) -> weather_app::Result<()> {
+    if args.csv {
+        writeln!(out, "{}", CSV_HEADER)?;
+    }
+    for (i, response) in reports.iter().enumerate() {
+        if i > 0 && mode.decorated() {
+            write_separator(out, options)?;
+        }
+        if let Some(template) = &args.format {
+            writeln!(
+                out,
+                "{}",
+                format_template(template, response, units, options)
+            )?;
+        } else if args.quiet {
+            writeln!(out, "{}", format_quiet(response, units, options))?;
+        } else if args.csv {
+            writeln!(out, "{}", format_csv_row(response, units))?;
+        } else if args.summary {
+            writeln!(out, "{}", format_summary(response, units))?;
+        } else if args.json {
+            let envelope = JsonEnvelope::from(WeatherSummary::new(response, units));
+            writeln!(out, "{}", serde_json::to_string_pretty(&envelope)?)?;
+        } else {
+            write_report(out, response, units, options)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reports each failed location on stderr, collecting its error into
+/// `failures`, and returns the rest.
+async fn successes<T, P: WeatherProvider>(
+    global: &GlobalArgs,
+    provider: &P,
+    locations: &[Location],
+    results: Vec<weather_app::Result<T>>,
+    failures: &mut Vec<Error>,
+) -> Vec<T> {
+    let mut successes = Vec::new();
+    for (location, result) in locations.iter().zip(results) {
+        match result {
+            Ok(value) => successes.push(value),
+            Err(e) => {
+                // Offline there's nothing to look the suggestions up in
+                let e = if global.offline {
+                    e
+                } else {
+                    did_you_mean(provider, location, e).await
+                };
+                eprintln!("{}: {}", location, e);
+                failures.push(e);
+            }
+        }
+    }
+    successes
+}
+
+/// Renders to stdout and, with `--output`, appends a color-free copy to the
+/// log file under a timestamp header.
+fn emit(
+    global: &GlobalArgs,
+    render: impl Fn(&mut dyn Write) -> weather_app::Result<()>,
+) -> weather_app::Result<()> {
+    render(&mut io::stdout().lock())?;
+
+    if let Some(path) = &global.output {
+        let mut buffer = Vec::new();
+        render(&mut PlainWriter::new(&mut buffer, true))?;
+
+        let write_error =
+            |e: io::Error| Error::Other(format!("Failed to write {}: {}", path.display(), e));
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(write_error)?;
+        writeln!(
+            file,
+            "=== {} ===",
+            Local::now().format("%Y-%m-%d %H:%M:%S %:z")
+        )
+        .and_then(|_| file.write_all(&buffer))
+        .map_err(write_error)?;
+    }
 
     Ok(())
 }
+
+/// Fetches the current weather plus any optional extras that were asked for.
+/// Extras are best-effort: a failure is reported but doesn't discard the
+/// weather itself.
+async fn fetch_report<P: WeatherProvider>(
+    global: &GlobalArgs,
+    args: &CurrentArgs,
+    provider: &P,
+    location: &Location,
+) -> weather_app::Result<WeatherReport> {
+    let mut report = if global.offline {
+        provider.fetch_cached(location).await?
+    } else {
+        match provider.fetch(location).await {
+            // Better an old reading than none; keep the original error if
+            // nothing was cached
+            Err(Error::Network(message)) => {
+                warn!(%location, error = %message, "API unreachable, trying the cache");
+                provider
+                    .fetch_cached(location)
+                    .await
+                    .map_err(|_| Error::Network(message))?
+            }
+            result => result?,
+        }
+    };
+
+    // Readings are remembered alongside cached responses, so --no-cache
+    // turns the trend off too
+    let history = if global.no_cache {
+        None
+    } else {
+        Cache::open(
+            global.cache_dir.clone(),
+            Duration::from_secs(global.cache_ttl * 60),
+        )?
+    };
+    if let Some(history) = &history {
+        let current = trend::Reading {
+            observed_at: report.observed_at,
+            temp: report.temp,
+            pressure: Some(report.pressure),
+        };
+        if let Some(previous) = trend::record(history, &report.location, current) {
+            report.trend = Some(report.temp - previous.temp);
+            report.pressure_trend = previous.pressure.map(|pressure| report.pressure - pressure);
+        }
+    }
+
+    if args.air_quality {
+        match provider.fetch_air_quality(report.lat, report.lon).await {
+            Ok(air_quality) => report.air_quality = Some(air_quality),
+            Err(e) => eprintln!("{}: {}", location, e),
+        }
+    }
+
+    if args.uv {
+        match provider.fetch_uv_index(report.lat, report.lon).await {
+            Ok(uv_index) => report.uv_index = Some(uv_index),
+            Err(e) => eprintln!("{}: {}", location, e),
+        }
+    }
+
+    if args.anomaly {
+        // Today where the location is, not where the user is
+        let date = (Utc::now() + TimeDelta::seconds(report.timezone.into())).date_naive();
+        match provider
+            .fetch_seasonal_average(report.lat, report.lon, date)
+            .await
+        {
+            Ok(average) => report.anomaly = Some(report.temp - average),
+            Err(e) => eprintln!("{}: {}", location, e),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_cities_trims_and_skips_blanks_and_comments() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let cities = dir.path().join("cities.txt");
+        fs::write(
+            &cities,
+            "  Seattle \n\n# West coast\n\tTokyo\r\n   \nSão Paulo, BR\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_cities(&cities).unwrap(),
+            ["Seattle", "Tokyo", "São Paulo, BR"]
+        );
+
+        let empty = dir.path().join("empty.txt");
+        fs::write(&empty, "# nothing yet\n\n").unwrap();
+        let error = read_cities(&empty).unwrap_err();
+        assert_eq!(error.exit_code(), 2);
+        assert!(error.to_string().starts_with("No cities found in"));
+
+        let missing = read_cities(&dir.path().join("missing.txt")).unwrap_err();
+        assert_eq!(missing.exit_code(), 2);
+    }
+}
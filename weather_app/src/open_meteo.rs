@@ -0,0 +1,461 @@
+use crate::client::USER_AGENT;
+use crate::provider::{place_name, Provider, WeatherProvider, WeatherReport, SUGGESTION_LIMIT};
+use crate::{Error, Location, Precipitation, Result, Weather};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+
+/// Wraps a non-zero hourly amount in mm the way OpenWeatherMap reports it.
+fn last_hour(mm: f64) -> Option<Precipitation> {
+    (mm > 0.0).then_some(Precipitation {
+        one_hour: Some(mm),
+        three_hours: None,
+    })
+}
+
+/// Query for the current conditions and today's range at `lat`/`lon`.
+fn current_query(lat: f64, lon: f64) -> [(&'static str, String); 8] {
+    [
+        ("latitude", lat.to_string()),
+        ("longitude", lon.to_string()),
+        (
+            "current",
+            "temperature_2m,relative_humidity_2m,apparent_temperature,weather_code,cloud_cover,rain,snowfall,\
+             pressure_msl,wind_speed_10m,wind_direction_10m,wind_gusts_10m"
+                .to_string(),
+        ),
+        (
+            "daily",
+            "temperature_2m_max,temperature_2m_min,sunrise,sunset".to_string(),
+        ),
+        ("wind_speed_unit", "ms".to_string()),
+        ("timeformat", "unixtime".to_string()),
+        ("timezone", "auto".to_string()),
+        ("forecast_days", "1".to_string()),
+    ]
+}
+
+/// Open-Meteo backend. Needs no API key, but only understands coordinates,
+/// so city names are resolved through Open-Meteo's geocoding API first.
+#[derive(Debug, Clone)]
+pub struct OpenMeteoClient {
+    http: reqwest::Client,
+    timeout: Duration,
+    geocoding_url: String,
+    country: Option<String>,
+    verbose: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GeocodingResult {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    country_code: Option<String>,
+}
+
+impl GeocodingResult {
+    /// The place name with its country code, e.g. `London, GB`.
+    fn display_name(&self) -> String {
+        match &self.country_code {
+            Some(country) => format!("{}, {}", self.name, country),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// The distinct names among the best-ranked geocoding results, at most
+/// `SUGGESTION_LIMIT` of them.
+fn suggestion_names(response: &GeocodingResponse) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for result in &response.results {
+        let name = result.display_name();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        if names.len() == SUGGESTION_LIMIT {
+            break;
+        }
+    }
+    names
+}
+
+#[derive(Deserialize, Debug)]
+struct ForecastResponse {
+    #[serde(default)]
+    utc_offset_seconds: i32,
+    current: Current,
+    daily: Daily,
+}
+
+#[derive(Deserialize, Debug)]
+struct Current {
+    #[serde(default)]
+    time: i64,
+    temperature_2m: f64,
+    relative_humidity_2m: i32,
+    cloud_cover: i32,
+    /// Rain over the preceding hour in mm
+    #[serde(default)]
+    rain: f64,
+    /// Snowfall over the preceding hour in cm
+    #[serde(default)]
+    snowfall: f64,
+    apparent_temperature: f64,
+    weather_code: u8,
+    pressure_msl: f64,
+    wind_speed_10m: f64,
+    wind_direction_10m: Option<f64>,
+    wind_gusts_10m: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct UvResponse {
+    current: UvCurrent,
+}
+
+#[derive(Deserialize, Debug)]
+struct UvCurrent {
+    uv_index: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Daily {
+    temperature_2m_max: Vec<f64>,
+    temperature_2m_min: Vec<f64>,
+    sunrise: Vec<i64>,
+    sunset: Vec<i64>,
+}
+
+impl OpenMeteoClient {
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(USER_AGENT)
+            .build()
+            .map_err(|e| Error::Other(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(OpenMeteoClient {
+            http,
+            timeout,
+            geocoding_url: GEOCODING_URL.to_string(),
+            country: None,
+            verbose: false,
+        })
+    }
+
+    /// Sends city name lookups to another server, e.g. a mock in tests.
+    pub fn with_geocoding_url(mut self, url: impl Into<String>) -> Self {
+        self.geocoding_url = url.into();
+        self
+    }
+
+    /// Restricts city name lookups to an ISO 3166 country code.
+    pub fn with_country(mut self, country: Option<String>) -> Self {
+        self.country = country;
+        self
+    }
+
+    /// Echoes every request URL and raw response body to stderr.
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    #[tracing::instrument(name = "fetch", level = "debug", skip_all, fields(%what, %url))]
+    async fn get_text(&self, url: &str, query: &[(&str, String)], what: &str) -> Result<String> {
+        let request = self
+            .http
+            .get(url)
+            .query(query)
+            .build()
+            .map_err(|e| Error::from_request(e, what, self.timeout))?;
+        debug!(url = %request.url(), "sending request");
+        if self.verbose {
+            eprintln!("> GET {}", request.url());
+        }
+        let response = self
+            .http
+            .execute(request)
+            .await
+            .map_err(|e| Error::from_request(e, what, self.timeout))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::from_request(e, what, self.timeout))?;
+        debug!(%status, bytes = body.len(), "response received");
+        if self.verbose {
+            eprintln!("< {}\n{}", status, body);
+        }
+        if !status.is_success() {
+            warn!(%status, "API returned an error");
+            return Err(Error::Api(format!("Open-Meteo returned {}", status)));
+        }
+        Ok(body)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+        what: &str,
+    ) -> Result<T> {
+        let body = self.get_text(url, query, what).await?;
+        tracing::debug_span!("parse", what).in_scope(|| {
+            serde_json::from_str(&body).map_err(|e| {
+                warn!(error = %e, "response didn't parse");
+                Error::Api(format!("Failed to parse {} data: {}", what, e))
+            })
+        })
+    }
+
+    /// Close matches for a misspelled `city`, best first. Open-Meteo's
+    /// geocoding search is fuzzy, so this works for typos that an exact
+    /// lookup will never find.
+    pub async fn suggest(&self, city: &str) -> Result<Vec<String>> {
+        let mut query = vec![
+            ("name", city.to_string()),
+            // Ask for a few extra in case some share a name and country
+            ("count", (SUGGESTION_LIMIT * 2).to_string()),
+        ];
+        if let Some(country) = &self.country {
+            query.push(("countryCode", country.clone()));
+        }
+        let response: GeocodingResponse = self
+            .get_json(&self.geocoding_url, &query, "geocoding")
+            .await?;
+        Ok(suggestion_names(&response))
+    }
+
+    /// Resolves `location` to coordinates and a display name.
+    async fn resolve(&self, location: &Location) -> Result<(f64, f64, String)> {
+        match location {
+            Location::Coordinates { lat, lon } => Ok((*lat, *lon, location.to_string())),
+            Location::City(city) => {
+                let mut query = vec![("name", city.clone()), ("count", "1".to_string())];
+                if let Some(country) = &self.country {
+                    query.push(("countryCode", country.clone()));
+                }
+                let response: GeocodingResponse = self
+                    .get_json(&self.geocoding_url, &query, "geocoding")
+                    .await?;
+                let result = response
+                    .results
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| Error::NotFound(format!("City '{}' not found", city)))?;
+                Ok((result.latitude, result.longitude, result.display_name()))
+            }
+            Location::Zip { .. } => Err(Error::Config(
+                "ZIP code lookups are only supported by OpenWeatherMap".to_string(),
+            )),
+            Location::CityId(_) => Err(Error::Config(
+                "City IDs are only supported by OpenWeatherMap".to_string(),
+            )),
+        }
+    }
+}
+
+impl WeatherProvider for OpenMeteoClient {
+    fn name(&self) -> Provider {
+        Provider::OpenMeteo
+    }
+
+    async fn suggest(&self, city: &str) -> Result<Vec<String>> {
+        OpenMeteoClient::suggest(self, place_name(city)).await
+    }
+
+    async fn fetch(&self, location: &Location) -> Result<WeatherReport> {
+        let (lat, lon, name) = self.resolve(location).await?;
+        let forecast: ForecastResponse = self
+            .get_json(FORECAST_URL, &current_query(lat, lon), "weather")
+            .await?;
+
+        let current = forecast.current;
+        let daily = forecast.daily;
+        let (main, description) = wmo_condition(current.weather_code);
+        Ok(WeatherReport {
+            location: name,
+            lat,
+            lon,
+            weather: Some(Weather {
+                main: main.to_string(),
+                description: description.to_string(),
+            }),
+            temp: current.temperature_2m + 273.15,
+            feels_like: current.apparent_temperature + 273.15,
+            temp_min: daily
+                .temperature_2m_min
+                .first()
+                .copied()
+                .unwrap_or(current.temperature_2m)
+                + 273.15,
+            temp_max: daily
+                .temperature_2m_max
+                .first()
+                .copied()
+                .unwrap_or(current.temperature_2m)
+                + 273.15,
+            humidity: current.relative_humidity_2m,
+            pressure: current.pressure_msl.round() as i32,
+            visibility: None,
+            clouds: current.cloud_cover,
+            rain: last_hour(current.rain),
+            snow: last_hour(current.snowfall * 10.0),
+            wind_speed: current.wind_speed_10m,
+            wind_deg: current.wind_direction_10m,
+            wind_gust: current.wind_gusts_10m,
+            sunrise: daily.sunrise.first().copied().unwrap_or_default(),
+            sunset: daily.sunset.first().copied().unwrap_or_default(),
+            timezone: forecast.utc_offset_seconds,
+            observed_at: current.time,
+            air_quality: None,
+            uv_index: None,
+            trend: None,
+            pressure_trend: None,
+            anomaly: None,
+            stale: false,
+        })
+    }
+
+    async fn fetch_raw(&self, location: &Location) -> Result<String> {
+        let (lat, lon, _) = self.resolve(location).await?;
+        self.get_text(FORECAST_URL, &current_query(lat, lon), "weather")
+            .await
+    }
+
+    async fn fetch_uv_index(&self, lat: f64, lon: f64) -> Result<f64> {
+        let query = [
+            ("latitude", lat.to_string()),
+            ("longitude", lon.to_string()),
+            ("current", "uv_index".to_string()),
+        ];
+        let response: UvResponse = self.get_json(FORECAST_URL, &query, "UV index").await?;
+        Ok(response.current.uv_index)
+    }
+}
+
+/// Maps a WMO weather interpretation code to an OpenWeatherMap-style
+/// condition group and description, so the usual emoji lookup applies.
+pub fn wmo_condition(code: u8) -> (&'static str, &'static str) {
+    match code {
+        0 => ("Clear", "clear sky"),
+        1 => ("Clear", "mainly clear"),
+        2 => ("Clouds", "partly cloudy"),
+        3 => ("Clouds", "overcast"),
+        45 | 48 => ("Fog", "fog"),
+        51 | 53 | 55 => ("Drizzle", "drizzle"),
+        56 | 57 => ("Drizzle", "freezing drizzle"),
+        61 | 63 | 65 => ("Rain", "rain"),
+        66 | 67 => ("Rain", "freezing rain"),
+        80..=82 => ("Rain", "rain showers"),
+        71 | 73 | 75 => ("Snow", "snow"),
+        77 => ("Snow", "snow grains"),
+        85 | 86 => ("Snow", "snow showers"),
+        95 => ("Thunderstorm", "thunderstorm"),
+        96 | 99 => ("Thunderstorm", "thunderstorm with hail"),
+        _ => ("", "unknown"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn typos_get_fuzzy_suggestions() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("name", "Londn, GB"))
+            .and(query_param("count", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(query_param("name", "Londn"))
+            .and(query_param("count", (SUGGESTION_LIMIT * 2).to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"results": [
+                    {"name": "London", "latitude": 51.5, "longitude": -0.1, "country_code": "GB"},
+                    {"name": "Londrina", "latitude": -23.3, "longitude": -51.2, "country_code": "BR"}
+                ]}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OpenMeteoClient::new(Duration::from_secs(5))
+            .unwrap()
+            .with_geocoding_url(server.uri());
+        let location = Location::City("Londn, GB".to_string());
+        let error = client.fetch(&location).await.unwrap_err();
+        let error = crate::did_you_mean(&client, &location, error).await;
+        assert_eq!(
+            error.to_string(),
+            "City 'Londn, GB' not found. Did you mean: London, GB? Londrina, BR?"
+        );
+    }
+
+    #[test]
+    fn wmo_condition_maps_to_emoji_groups() {
+        assert_eq!(wmo_condition(0).0, "Clear");
+        assert_eq!(wmo_condition(3).0, "Clouds");
+        assert_eq!(wmo_condition(48).0, "Fog");
+        assert_eq!(wmo_condition(81).0, "Rain");
+        assert_eq!(wmo_condition(86).0, "Snow");
+        assert_eq!(wmo_condition(99).0, "Thunderstorm");
+    }
+
+    #[test]
+    fn suggestions_are_distinct_and_limited() {
+        let body = r#"{"results": [
+            {"name": "London", "latitude": 51.5, "longitude": -0.1, "country_code": "GB"},
+            {"name": "London", "latitude": 51.4, "longitude": -0.3, "country_code": "GB"},
+            {"name": "London", "latitude": 43.0, "longitude": -81.2, "country_code": "CA"},
+            {"name": "Londonderry", "latitude": 55.0, "longitude": -7.3, "country_code": "GB"},
+            {"name": "Lund", "latitude": 55.7, "longitude": 13.2, "country_code": "SE"}
+        ]}"#;
+        let response: GeocodingResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            suggestion_names(&response),
+            vec!["London, GB", "London, CA", "Londonderry, GB"]
+        );
+        assert!(suggestion_names(&serde_json::from_str(r#"{}"#).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn forecast_response_maps_into_report_shape() {
+        let body = r#"{
+            "current": {
+                "temperature_2m": 12.3,
+                "relative_humidity_2m": 76,
+                "cloud_cover": 40,
+                "apparent_temperature": 10.1,
+                "weather_code": 3,
+                "pressure_msl": 1012.4,
+                "wind_speed_10m": 4.1,
+                "wind_direction_10m": 250
+            },
+            "daily": {
+                "temperature_2m_max": [14.0],
+                "temperature_2m_min": [9.5],
+                "sunrise": [1700000000],
+                "sunset": [1700030000]
+            }
+        }"#;
+        let forecast: ForecastResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(forecast.current.weather_code, 3);
+        assert_eq!(forecast.daily.sunrise, vec![1700000000]);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{
+    AirQuality, Error, ForecastResponse, Location, Precipitation, Result, Weather, WeatherClient,
+    WeatherResponse,
+};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use std::fmt;
+
+/// How many close matches are offered for a city that wasn't found.
+pub const SUGGESTION_LIMIT: usize = 3;
+
+/// The place name in `city`, without any ",state,country" qualifiers.
+pub(crate) fn place_name(city: &str) -> &str {
+    city.split(',').next().unwrap_or(city).trim()
+}
+
+/// Backend the weather is fetched from, chosen with `--provider`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Provider {
+    /// OpenWeatherMap (requires an API key)
+    #[default]
+    #[value(name = "openweathermap")]
+    OpenWeatherMap,
+    /// Open-Meteo (no API key needed)
+    OpenMeteo,
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::OpenWeatherMap => write!(f, "OpenWeatherMap"),
+            Provider::OpenMeteo => write!(f, "Open-Meteo"),
+        }
+    }
+}
+
+/// Current conditions in a provider-independent shape. Temperatures are in
+/// Kelvin and speeds in m/s so every backend goes through the same unit
+/// conversion when rendered.
+#[derive(Debug, Clone)]
+pub struct WeatherReport {
+    pub location: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub weather: Option<Weather>,
+    pub temp: f64,
+    pub feels_like: f64,
+    pub temp_min: f64,
+    pub temp_max: f64,
+    pub humidity: i32,
+    pub pressure: i32,
+    pub visibility: Option<i32>,
+    /// Cloud cover in percent
+    pub clouds: i32,
+    pub rain: Option<Precipitation>,
+    pub snow: Option<Precipitation>,
+    pub wind_speed: f64,
+    pub wind_deg: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub sunrise: i64,
+    pub sunset: i64,
+    /// Offset from UTC in seconds for the location's local time
+    pub timezone: i32,
+    /// When the observation was made, as a Unix timestamp
+    pub observed_at: i64,
+    /// Only filled in when `--air-quality` is requested
+    pub air_quality: Option<AirQuality>,
+    /// Only filled in when `--uv` is requested
+    pub uv_index: Option<f64>,
+    /// Change in Kelvin since the previous reading, when one was recorded
+    pub trend: Option<f64>,
+    /// Change in hPa since the previous reading, when one was recorded with
+    /// a pressure
+    pub pressure_trend: Option<i32>,
+    /// Difference in Kelvin from the seasonal average; only filled in when
+    /// `--anomaly` is requested
+    pub anomaly: Option<f64>,
+    /// Served from the cache because the API couldn't be reached
+    pub stale: bool,
+}
+
+impl From<WeatherResponse> for WeatherReport {
+    fn from(response: WeatherResponse) -> Self {
+        WeatherReport {
+            location: response.name,
+            lat: response.coord.lat,
+            lon: response.coord.lon,
+            weather: response.weather.into_iter().next(),
+            temp: response.main.temp,
+            feels_like: response.main.feels_like,
+            temp_min: response.main.temp_min,
+            temp_max: response.main.temp_max,
+            humidity: response.main.humidity,
+            pressure: response.main.pressure,
+            visibility: response.visibility,
+            clouds: response.clouds.all,
+            rain: response.rain,
+            snow: response.snow,
+            wind_speed: response.wind.speed,
+            wind_deg: response.wind.deg,
+            wind_gust: response.wind.gust,
+            sunrise: response.sys.sunrise,
+            sunset: response.sys.sunset,
+            timezone: response.timezone,
+            observed_at: response.dt,
+            air_quality: None,
+            uv_index: None,
+            trend: None,
+            pressure_trend: None,
+            anomaly: None,
+            stale: false,
+        }
+    }
+}
+
+/// A source of weather data that can be rendered by the shared report code.
+// Only used with concrete types inside this crate, so the missing `Send`
+// bound on the returned futures doesn't matter.
+#[allow(async_fn_in_trait)]
+pub trait WeatherProvider {
+    fn name(&self) -> Provider;
+
+    async fn fetch(&self, location: &Location) -> Result<WeatherReport>;
+
+    /// The last reading cached for `location`, however old, without going
+    /// to the network.
+    async fn fetch_cached(&self, _location: &Location) -> Result<WeatherReport> {
+        Err(Error::Config(format!(
+            "Cached readings are not available from {}",
+            self.name()
+        )))
+    }
+
+    /// The provider's current-weather response body, as received.
+    async fn fetch_raw(&self, _location: &Location) -> Result<String> {
+        Err(Error::Config(format!(
+            "Raw responses are not available from {}",
+            self.name()
+        )))
+    }
+
+    /// The URL of the first request fetching the weather (or with
+    /// `forecast`, the forecast) for `location` would send, for `--dry-run`.
+    fn request_url(&self, _location: &Location, _forecast: bool) -> Result<String> {
+        Err(Error::Config(format!(
+            "Dry runs are not available from {}",
+            self.name()
+        )))
+    }
+
+    async fn fetch_forecast(&self, _location: &Location) -> Result<ForecastResponse> {
+        Err(Error::Config(format!(
+            "Forecasts are not available from {}",
+            self.name()
+        )))
+    }
+
+    /// Places close to `city`, as typed with any qualifiers, best first.
+    /// Looked up through the provider's own geocoding so the name isn't
+    /// sent anywhere else.
+    async fn suggest(&self, _city: &str) -> Result<Vec<String>> {
+        Err(Error::Config(format!(
+            "Suggestions are not available from {}",
+            self.name()
+        )))
+    }
+
+    async fn fetch_air_quality(&self, _lat: f64, _lon: f64) -> Result<AirQuality> {
+        Err(Error::Config(format!(
+            "Air quality is not available from {}",
+            self.name()
+        )))
+    }
+
+    async fn fetch_uv_index(&self, _lat: f64, _lon: f64) -> Result<f64> {
+        Err(Error::Config(format!(
+            "The UV index is not available from {}",
+            self.name()
+        )))
+    }
+
+    /// Typical temperature in Kelvin for `date`'s day of the year.
+    async fn fetch_seasonal_average(&self, _lat: f64, _lon: f64, _date: NaiveDate) -> Result<f64> {
+        Err(Error::Config(format!(
+            "The seasonal average is not available from {}",
+            self.name()
+        )))
+    }
+}
+
+impl WeatherProvider for WeatherClient {
+    fn name(&self) -> Provider {
+        Provider::OpenWeatherMap
+    }
+
+    /// City names are resolved through the geocoding API first so the
+    /// report uses the canonical place name and exact coordinates.
+    async fn fetch(&self, location: &Location) -> Result<WeatherReport> {
+        match location {
+            Location::City(city) => {
+                let (lat, lon, name) = self.geocode(city).await?;
+                let response = self
+                    .fetch_weather(&Location::Coordinates { lat, lon })
+                    .await?;
+                let mut report = WeatherReport::from(response);
+                report.location = name;
+                Ok(report)
+            }
+            _ => self.fetch_weather(location).await.map(WeatherReport::from),
+        }
+    }
+
+    async fn fetch_raw(&self, location: &Location) -> Result<String> {
+        self.fetch_weather_raw(location).await
+    }
+
+    fn request_url(&self, location: &Location, forecast: bool) -> Result<String> {
+        WeatherClient::request_url(self, location, forecast)
+    }
+
+    async fn fetch_cached(&self, location: &Location) -> Result<WeatherReport> {
+        let offline = self.clone().with_offline(true);
+        let mut report = WeatherProvider::fetch(&offline, location).await?;
+        report.stale = true;
+        Ok(report)
+    }
+
+    async fn fetch_forecast(&self, location: &Location) -> Result<ForecastResponse> {
+        match location {
+            Location::City(city) => {
+                let (lat, lon, name) = self.geocode(city).await?;
+                let mut forecast =
+                    WeatherClient::fetch_forecast(self, &Location::Coordinates { lat, lon })
+                        .await?;
+                forecast.city.name = name;
+                Ok(forecast)
+            }
+            _ => WeatherClient::fetch_forecast(self, location).await,
+        }
+    }
+
+    async fn suggest(&self, city: &str) -> Result<Vec<String>> {
+        WeatherClient::suggest(self, city).await
+    }
+
+    async fn fetch_air_quality(&self, lat: f64, lon: f64) -> Result<AirQuality> {
+        WeatherClient::fetch_air_quality(self, lat, lon).await
+    }
+
+    async fn fetch_uv_index(&self, lat: f64, lon: f64) -> Result<f64> {
+        WeatherClient::fetch_uv_index(self, lat, lon).await
+    }
+
+    async fn fetch_seasonal_average(&self, lat: f64, lon: f64, date: NaiveDate) -> Result<f64> {
+        WeatherClient::fetch_seasonal_average(self, lat, lon, date).await
+    }
+}
+
+/// Adds close matches from `provider` to the error for a city that wasn't
+/// found. Any other error, or a failed lookup, is returned unchanged.
+pub async fn did_you_mean<P: WeatherProvider>(
+    provider: &P,
+    location: &Location,
+    error: Error,
+) -> Error {
+    let (Error::NotFound(message), Location::City(city)) = (&error, location) else {
+        return error;
+    };
+    match provider.suggest(city).await {
+        Ok(mut names) if !names.is_empty() => {
+            names.truncate(SUGGESTION_LIMIT);
+            error.with_message(format!("{}. Did you mean: {}?", message, names.join("? ")))
+        }
+        _ => error,
+    }
+}
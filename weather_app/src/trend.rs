@@ -0,0 +1,152 @@
+use crate::Cache;
+use serde::{Deserialize, Serialize};
+
+/// How many readings are kept per location; older ones are dropped.
+pub const MAX_READINGS: usize = 48;
+
+/// Prefix of the cache keys readings are stored under.
+const KEY_PREFIX: &str = "trend-";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// When the observation was made, as a Unix timestamp
+    pub observed_at: i64,
+    /// Kelvin
+    pub temp: f64,
+    /// hPa; missing from readings recorded before pressure was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<i32>,
+}
+
+/// The most recent distinct observations for a location, oldest first.
+#[derive(Serialize, Deserialize, Debug)]
+struct History {
+    location: String,
+    readings: Vec<Reading>,
+}
+
+/// Records `current` as the latest reading for `location` and returns the
+/// one before it, if there was one, to compare against.
+///
+/// The same observation is often served twice, from the response cache or
+/// because the provider hasn't updated yet, so it only counts as a new reading
+/// when its observation time is later than the last one seen. Anything older
+/// than that is ignored.
+pub fn record(cache: &Cache, location: &str, current: Reading) -> Option<Reading> {
+    let key = format!("{}{}", KEY_PREFIX, location);
+
+    let mut history = cache
+        .get_stale(&key)
+        .and_then(|body| serde_json::from_str::<History>(&body).ok())
+        .unwrap_or_else(|| History {
+            location: location.to_string(),
+            readings: Vec::new(),
+        });
+
+    match history.readings.last() {
+        Some(latest) if latest.observed_at > current.observed_at => return None,
+        Some(latest) if latest.observed_at == current.observed_at => {}
+        _ => {
+            history.readings.push(current);
+            let excess = history.readings.len().saturating_sub(MAX_READINGS);
+            history.readings.drain(..excess);
+            if let Ok(body) = serde_json::to_string(&history) {
+                cache.put(&key, &body);
+            }
+        }
+    }
+
+    match history.readings.as_slice() {
+        [.., previous, _] => Some(*previous),
+        _ => None,
+    }
+}
+
+/// Every location with recorded readings whose name is `city` or starts with
+/// `city,` (so "London" finds "London, England, GB"), ignoring case.
+pub fn readings(cache: &Cache, city: &str) -> Vec<(String, Vec<Reading>)> {
+    let city = city.trim().to_lowercase();
+    let mut found: Vec<(String, Vec<Reading>)> = cache
+        .get_all_stale(KEY_PREFIX)
+        .iter()
+        .filter_map(|body| serde_json::from_str::<History>(body).ok())
+        .filter(|history| {
+            let location = history.location.to_lowercase();
+            location == city || location.starts_with(&format!("{},", city))
+        })
+        .map(|history| (history.location, history.readings))
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn reading(observed_at: i64, temp: f64) -> Reading {
+        Reading {
+            observed_at,
+            temp,
+            pressure: Some(1010),
+        }
+    }
+
+    #[test]
+    fn trend_compares_against_the_previous_observation() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::at(dir.path().to_path_buf(), Duration::from_secs(600));
+
+        assert_eq!(record(&cache, "London", reading(1_000, 285.0)), None);
+        // Same observation again (e.g. from the response cache): still compared
+        // with nothing, rather than with itself
+        assert_eq!(record(&cache, "London", reading(1_000, 285.0)), None);
+
+        let previous = record(&cache, "London", reading(2_000, 286.5));
+        assert_eq!(previous, Some(reading(1_000, 285.0)));
+        // Serving 2_000 again keeps comparing against 1_000
+        let previous = record(&cache, "London", reading(2_000, 286.5));
+        assert_eq!(previous, Some(reading(1_000, 285.0)));
+        // An older observation arriving late is ignored
+        assert_eq!(record(&cache, "London", reading(1_500, 280.0)), None);
+
+        assert_eq!(record(&cache, "Paris", reading(2_000, 290.0)), None);
+    }
+
+    #[test]
+    fn readings_from_before_pressure_was_kept_still_load() {
+        let history: History = serde_json::from_str(
+            r#"{"location": "London", "readings": [{"observed_at": 1000, "temp": 285.0}]}"#,
+        )
+        .unwrap();
+        assert_eq!(history.readings[0].pressure, None);
+    }
+
+    #[test]
+    fn readings_are_capped_and_found_by_city_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::at(dir.path().to_path_buf(), Duration::from_secs(600));
+
+        for i in 0..MAX_READINGS as i64 + 5 {
+            record(
+                &cache,
+                "London, England, GB",
+                reading(i * 600, 280.0 + i as f64),
+            );
+        }
+        record(
+            &cache,
+            "Londonderry, Northern Ireland, GB",
+            reading(0, 280.0),
+        );
+
+        let found = readings(&cache, "london");
+        assert_eq!(found.len(), 1);
+        let (location, stored) = &found[0];
+        assert_eq!(location, "London, England, GB");
+        assert_eq!(stored.len(), MAX_READINGS);
+        assert_eq!(stored[0].observed_at, 5 * 600);
+        assert!(readings(&cache, "Atlantis").is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    kelvin_to_celsius, kelvin_to_fahrenheit, meters_per_second_to_kmh, meters_per_second_to_knots,
+    meters_per_second_to_mph,
+};
+use clap::ValueEnum;
+
+/// Unit system chosen with `--units`, named after OpenWeatherMap's own.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Units {
+    /// Celsius and km/h
+    #[default]
+    Metric,
+    /// Fahrenheit and mph
+    Imperial,
+    /// Kelvin and m/s, exactly as returned by the API
+    Standard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TempUnit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TempUnit {
+    pub fn convert(self, kelvin: f64) -> f64 {
+        match self {
+            TempUnit::Celsius => kelvin_to_celsius(kelvin),
+            TempUnit::Fahrenheit => kelvin_to_fahrenheit(kelvin),
+            TempUnit::Kelvin => kelvin,
+        }
+    }
+
+    /// Converts a temperature difference in Kelvin to this unit's scale.
+    pub fn convert_delta(self, kelvin: f64) -> f64 {
+        match self {
+            TempUnit::Celsius | TempUnit::Kelvin => kelvin,
+            TempUnit::Fahrenheit => kelvin * 9.0 / 5.0,
+        }
+    }
+
+    /// Converts a value already in this unit back to Celsius.
+    pub fn to_celsius(self, value: f64) -> f64 {
+        match self {
+            TempUnit::Celsius => value,
+            TempUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+            TempUnit::Kelvin => kelvin_to_celsius(value),
+        }
+    }
+
+    /// Suffix printed after a temperature, e.g. `°C`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "°C",
+            TempUnit::Fahrenheit => "°F",
+            TempUnit::Kelvin => "K",
+        }
+    }
+
+    /// Short name used in machine-readable output.
+    pub fn code(self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "C",
+            TempUnit::Fahrenheit => "F",
+            TempUnit::Kelvin => "K",
+        }
+    }
+}
+
+/// Unit for wind speeds, chosen with `--wind-unit` or implied by `--units`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindUnit {
+    /// Kilometres per hour
+    Kmh,
+    /// Miles per hour
+    Mph,
+    /// Metres per second
+    #[value(name = "ms")]
+    MetersPerSecond,
+    /// Nautical miles per hour
+    Knots,
+}
+
+impl WindUnit {
+    pub fn convert(self, mps: f64) -> f64 {
+        match self {
+            WindUnit::Kmh => meters_per_second_to_kmh(mps),
+            WindUnit::Mph => meters_per_second_to_mph(mps),
+            WindUnit::MetersPerSecond => mps,
+            WindUnit::Knots => meters_per_second_to_knots(mps),
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            WindUnit::Kmh => "km/h",
+            WindUnit::Mph => "mph",
+            WindUnit::MetersPerSecond => "m/s",
+            WindUnit::Knots => "kn",
+        }
+    }
+}
+
+/// Decimal places shown unless `--precision` says otherwise.
+pub const DEFAULT_PRECISION: usize = 1;
+
+/// Most decimal places `--precision` accepts.
+pub const MAX_PRECISION: usize = 3;
+
+/// The units every value in a report is rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayUnits {
+    pub system: Units,
+    pub temperature: TempUnit,
+    pub wind: WindUnit,
+    /// Decimal places for temperatures, speeds and amounts
+    pub precision: usize,
+}
+
+impl DisplayUnits {
+    /// Derives display units from `--units`, with `--fahrenheit`/`--kelvin`
+    /// overriding only the temperature.
+    pub fn new(system: Units, temperature_override: Option<TempUnit>) -> Self {
+        let (temperature, wind) = match system {
+            Units::Metric => (TempUnit::Celsius, WindUnit::Kmh),
+            Units::Imperial => (TempUnit::Fahrenheit, WindUnit::Mph),
+            Units::Standard => (TempUnit::Kelvin, WindUnit::MetersPerSecond),
+        };
+        DisplayUnits {
+            system,
+            temperature: temperature_override.unwrap_or(temperature),
+            wind,
+            precision: DEFAULT_PRECISION,
+        }
+    }
+
+    /// Overrides the wind unit implied by the unit system, if `wind` is set.
+    pub fn with_wind_unit(mut self, wind: Option<WindUnit>) -> Self {
+        if let Some(wind) = wind {
+            self.wind = wind;
+        }
+        self
+    }
+
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Formats `value` with the configured number of decimal places.
+    pub fn number(&self, value: f64) -> String {
+        format!("{:.*}", self.precision, value)
+    }
+
+    /// Whether `value` would be shown as zero at this precision.
+    pub fn rounds_to_zero(&self, value: f64) -> bool {
+        value.abs() < 0.5 * 10f64.powi(-(self.precision as i32))
+    }
+}
+
+impl Default for DisplayUnits {
+    fn default() -> Self {
+        DisplayUnits::new(Units::default(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_units_follow_unit_system() {
+        let imperial = DisplayUnits::new(Units::Imperial, None);
+        assert_eq!(imperial.temperature, TempUnit::Fahrenheit);
+        assert_eq!(imperial.wind, WindUnit::Mph);
+
+        let standard = DisplayUnits::new(Units::Standard, None);
+        assert_eq!(standard.temperature, TempUnit::Kelvin);
+        assert_eq!(standard.wind, WindUnit::MetersPerSecond);
+
+        // --fahrenheit only changes the temperature
+        let metric = DisplayUnits::new(Units::Metric, Some(TempUnit::Fahrenheit));
+        assert_eq!(metric.temperature, TempUnit::Fahrenheit);
+        assert_eq!(metric.wind, WindUnit::Kmh);
+
+        let kelvin = DisplayUnits::new(Units::Imperial, Some(TempUnit::Kelvin));
+        assert_eq!(kelvin.temperature, TempUnit::Kelvin);
+        assert_eq!(kelvin.wind, WindUnit::Mph);
+    }
+
+    #[test]
+    fn wind_unit_overrides_only_the_wind() {
+        let mixed = DisplayUnits::new(Units::Metric, None).with_wind_unit(Some(WindUnit::Mph));
+        assert_eq!(mixed.temperature, TempUnit::Celsius);
+        assert_eq!(mixed.wind, WindUnit::Mph);
+        assert_eq!(
+            DisplayUnits::new(Units::Imperial, None)
+                .with_wind_unit(None)
+                .wind,
+            WindUnit::Mph
+        );
+
+        let knots = WindUnit::Knots;
+        assert_eq!(knots.symbol(), "kn");
+        assert!((knots.convert(10.0) - 19.438).abs() < 1e-3);
+    }
+
+    #[test]
+    fn precision_controls_decimal_places() {
+        let units = DisplayUnits::default();
+        assert_eq!(units.precision, DEFAULT_PRECISION);
+        assert_eq!(units.number(4.16), "4.2");
+        assert!(units.rounds_to_zero(0.04));
+        assert!(!units.rounds_to_zero(-0.05));
+
+        let whole = units.with_precision(0);
+        assert_eq!(whole.number(4.6), "5");
+        assert!(whole.rounds_to_zero(0.4));
+        assert_eq!(units.with_precision(MAX_PRECISION).number(4.16), "4.160");
+    }
+}